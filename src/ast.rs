@@ -1,400 +1,405 @@
+use crate::parser::{ParseError, Parser};
 use crate::token::*;
-use std::cell::{Ref, RefCell, RefMut};
-use std::rc::Rc;
+use crate::variable::VariableType;
 
 /**
- * Usage: merge operators
+ * Parse an integer literal
  *
- * For example:
- * ```
- * merge_op(&mut top, |ast| ast.borrow().r#type == op);
- * ```
- * Before:
- * ```text
- *  top
- * / | \
- * A op B
- * ```
- * After:
- * ```text
- *  top
- *   |
- *  op
- *  / \
- * A   B
- * ```
+ * Accepts decimal, `0x` hex, `0o` octal and `0b` binary digits separated by
+ * optional underlines, an optional leading `-` and an optional type suffix.
+ * For example: `1_000`, `0xFF`, `255u8`, `-5i32`.
 */
-fn merge_op<F>(top_ast: &mut AstNode, is_op: F)
-where
-    F: Fn(Rc<RefCell<AstNode>>) -> bool,
-{
-    let mut node_i = 0;
-    while node_i < top_ast.nodes.len() {
-        if is_op(Rc::clone(&top_ast.nodes[node_i])) {
-            let left = Rc::clone(&top_ast.nodes[node_i - 1]);
-            let right = Rc::clone(&top_ast.nodes[node_i + 1]);
-            top_ast.node_mut(node_i).push(left);
-            top_ast.node_mut(node_i).push(right);
-            /* remove left and right */
-            top_ast.remove(node_i - 1);
-            top_ast.remove(node_i);
-            node_i -= 1;
+pub fn parse_integer(text: &str) -> Result<(i128, Option<VariableType>), String> {
+    let (negative, literal) = match text.strip_prefix('-') {
+        Some(literal) => (true, literal),
+        None => (false, text),
+    };
+    let mut suffix = None;
+    let mut digits = literal;
+    for name in ["u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64"] {
+        if let Some(stripped) = literal.strip_suffix(name) {
+            suffix = VariableType::from_suffix(name);
+            digits = stripped;
+            break;
         }
-        node_i += 1;
     }
+    let (radix, digits) = if let Some(digits) = digits.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = digits.strip_prefix("0o") {
+        (8, digits)
+    } else if let Some(digits) = digits.strip_prefix("0b") {
+        (2, digits)
+    } else {
+        (10, digits)
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(format!("'{text}' is not a number."));
+    }
+    let value = match u128::from_str_radix(&digits, radix) {
+        Ok(value) if value <= u64::MAX as u128 => value as i128,
+        _ => return Err(format!("integer literal '{text}' is too large")),
+    };
+    let value = if negative { -value } else { value };
+    let var_type = suffix.clone().unwrap_or(VariableType::Unkown);
+    if !var_type.contains(value) {
+        return Err(format!(
+            "integer literal '{text}' is out of range for {var_type}"
+        ));
+    }
+    Ok((value, suffix))
 }
 
-#[derive(Clone, Default)]
-pub struct AstNode {
-    pub r#type: u8,
-    pub data: String,
-    pub nodes: Vec<Rc<RefCell<AstNode>>>,
+/** A whole program */
+#[derive(Clone, Debug, Default)]
+pub struct Ast {
+    pub items: Vec<Item>,
 }
 
-impl AstNode {
-    pub fn new() -> Self {
-        AstNode::default()
-    }
-    /** push a subnode */
-    pub fn push(&mut self, node: Rc<RefCell<AstNode>>) {
-        self.nodes.push(Rc::clone(&node));
-    }
-    /** remove a subnode */
-    pub fn remove(&mut self, index: usize) {
-        self.nodes.remove(index);
+impl Ast {
+    pub fn from_tokens(tokens: Vec<Token>) -> Result<Self, Vec<ParseError>> {
+        Parser::new(tokens).parse()
     }
-    /** get mutable reference of refcell subnode */
-    pub fn node_mut(&self, index: usize) -> RefMut<'_, AstNode> {
-        self.nodes[index].borrow_mut()
-    }
-    /** get ref of refcell subnode */
-    pub fn node(&self, index: usize) -> Ref<'_, AstNode> {
-        self.nodes[index].borrow()
-    }
-    pub fn is_operator(&self) -> bool {
-        self.r#type == AST_TYPE_ADD
-            || self.r#type == AST_TYPE_SUB
-            || self.r#type == AST_TYPE_MUL
-            || self.r#type == AST_TYPE_DIV
-            || self.r#type == AST_TYPE_MOD
-            || self.r#type == AST_TYPE_AND
-            || self.r#type == AST_TYPE_OR
-            || self.r#type == AST_TYPE_XOR
-            || self.r#type == AST_TYPE_SHL
-            || self.r#type == AST_TYPE_SHR
-    }
-    pub fn get_value(&self) -> Result<u64, String> {
-        if self.r#type == AST_TYPE_VALUE {
-            match self.data.parse::<u64>() {
-                Ok(val) => return Ok(val),
-                Err(_) => return Err(format!("\'{}\' is not a number.", &self.data)),
-            }
+}
+
+/** Top level definitions */
+#[derive(Clone, Debug)]
+pub enum Item {
+    Func(FuncDef),
+    Global(VarDecl),
+}
+
+/** A name written in the source code, like a variable, a function or a type */
+#[derive(Clone, Debug, Default)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct FuncDef {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub ret_type: Option<Ident>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: Ident,
+    pub var_type: Ident,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+/**
+ * `var name: type` with an optional initial value,
+ * the type is inferred from the initial value if it is left out like `var name = value`
+ */
+#[derive(Clone, Debug)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub var_type: Option<Ident>,
+    pub init: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum StmtKind {
+    Var(VarDecl),
+    /**
+     * `target = value`, or `target [op]= value` if `op` is set.
+     * `i++` and `i--` are parsed as `i += 1` and `i -= 1`.
+     */
+    Assign {
+        target: Ident,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    Expr(Expr),
+    If(IfStmt),
+    /** `'label: while cond {...}`, the label is optional */
+    While {
+        label: Option<Ident>,
+        cond: Expr,
+        body: Block,
+    },
+    For(ForStmt),
+    Return(Option<Expr>),
+    /** `break` or `break 'label` */
+    Break(Option<Ident>),
+    /** `continue` or `continue 'label` */
+    Continue(Option<Ident>),
+    Block(Block),
+}
+
+/** `if cond {...}` followed by an optional `elif` or `else` branch */
+#[derive(Clone, Debug)]
+pub struct IfStmt {
+    pub cond: Expr,
+    pub body: Block,
+    pub else_branch: Option<ElseBranch>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum ElseBranch {
+    Elif(Box<IfStmt>),
+    Else(Block),
+}
+
+/** `for i in start..end {...}` or `for (init; cond; step) {...}` with an optional label */
+#[derive(Clone, Debug)]
+pub struct ForStmt {
+    pub label: Option<Ident>,
+    pub header: ForHeader,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum ForHeader {
+    /**
+     * `for var: type in start..end step n`, the type and the step are optional.
+     * `var` counts from `start` up to but not including `end`.
+     */
+    Range {
+        var: Ident,
+        var_type: Option<Ident>,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        step: Option<Box<Expr>>,
+    },
+    /** `for (init; cond; step)`, every part can be empty */
+    CStyle {
+        init: Option<Box<Stmt>>,
+        cond: Option<Expr>,
+        step: Option<Box<Stmt>>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+    /** type resolved by `check::Checker`, `None` before checking or for calls without return type */
+    pub r#type: Option<VariableType>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExprKind {
+    Literal(Literal),
+    Identifier(String),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Call {
+        name: Ident,
+        args: Vec<Expr>,
+    },
+    /** `base[index]` */
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /** `base.member` */
+    Member {
+        base: Box<Expr>,
+        member: Ident,
+    },
+    /** `operand as type` */
+    Cast {
+        operand: Box<Expr>,
+        target: Ident,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    /** integer with an optional type suffix */
+    Integer {
+        value: i128,
+        suffix: Option<VariableType>,
+    },
+    Bool(bool),
+    Char(char),
+    String(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,      // +
+    Sub,      // -
+    Mul,      // *
+    Div,      // /
+    Mod,      // %
+    Shl,      // <<
+    Shr,      // >>
+    And,      // &
+    Or,       // |
+    Xor,      // ^
+    LogicAnd, // &&
+    LogicOr,  // ||
+    Equ,      // ==
+    NotEqu,   // !=
+    GT,       // >
+    LT,       // <
+    Ge,       // >=
+    Le,       // <=
+}
+
+impl BinaryOp {
+    /** priority in expressions like C, higher binds tighter */
+    pub fn priority(&self) -> u8 {
+        match self {
+            Self::LogicOr => 1,
+            Self::LogicAnd => 2,
+            Self::Or => 3,
+            Self::Xor => 4,
+            Self::And => 5,
+            Self::Equ | Self::NotEqu => 6,
+            Self::GT | Self::LT | Self::Ge | Self::Le => 7,
+            Self::Shl | Self::Shr => 8,
+            Self::Add | Self::Sub => 9,
+            Self::Mul | Self::Div | Self::Mod => 10,
         }
-        Err(String::new())
     }
-    /** returns a top node that typed `AST_TYPE_PARAMS` */
-    pub fn get_params(&self) -> Option<Ref<AstNode>> {
-        if self.r#type == AST_TYPE_FUNC_CALL {
-            return Some(self.node(1));
-        }
-        if self.r#type == AST_TYPE_IF || self.r#type == AST_TYPE_WHILE {
-            return Some(self.node(0));
-        }
-        None
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Equ | Self::NotEqu | Self::GT | Self::LT | Self::Ge | Self::Le
+        )
     }
-    pub fn get_code_block(&self) -> Option<Ref<AstNode>> {
-        if self.r#type == AST_TYPE_FUNC_DEF {
-            return Some(self.node(2));
-        }
-        if self.r#type == AST_TYPE_IF || self.r#type == AST_TYPE_WHILE {
-            return Some(self.node(1));
-        }
-        None
+}
+
+impl std::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Shl => "<<",
+            Self::Shr => ">>",
+            Self::And => "&",
+            Self::Or => "|",
+            Self::Xor => "^",
+            Self::LogicAnd => "&&",
+            Self::LogicOr => "||",
+            Self::Equ => "==",
+            Self::NotEqu => "!=",
+            Self::GT => ">",
+            Self::LT => "<",
+            Self::Ge => ">=",
+            Self::Le => "<=",
+        };
+        write!(f, "{op}")
     }
-    pub fn from_tokens<T>(tokens: &mut T) -> Self
-    where
-        T: Iterator<Item = Token>,
-    {
-        let mut top_ast = AstNode::new();
-        while let Some(token) = tokens.next() {
-            let mut new_node = AstNode::new();
-            new_node.data.clone_from(&token.name);
-            /* keywords */
-            if token.r#type == TokenType::Keyword {
-                match &token.name[..] {
-                    "func" => new_node.r#type = AST_TYPE_FUNC_DEF,
-                    "var" => new_node.r#type = AST_TYPE_VAR_DECLARE,
-                    "if" => new_node.r#type = AST_TYPE_IF,
-                    "elif" => new_node.r#type = AST_TYPE_ELIF,
-                    "else" => new_node.r#type = AST_TYPE_ELSE,
-                    "for" => new_node.r#type = AST_TYPE_FOR,
-                    "while" => new_node.r#type = AST_TYPE_WHILE,
-                    "break" => new_node.r#type = AST_TYPE_BREAK,
-                    "continue" => new_node.r#type = AST_TYPE_CONTINUE,
-                    "return" => new_node.r#type = AST_TYPE_RETURN,
-                    "true" => new_node.r#type = AST_TYPE_VALUE,
-                    "false" => new_node.r#type = AST_TYPE_VALUE,
-                    _ => {}
-                }
-            }
-            match token.r#type {
-                TokenType::Number => new_node.r#type = AST_TYPE_VALUE,
-                TokenType::String => new_node.r#type = AST_TYPE_VALUE,
-                TokenType::Char => new_node.r#type = AST_TYPE_VALUE,
-                TokenType::Add => new_node.r#type = AST_TYPE_ADD, // +
-                TokenType::Sub => new_node.r#type = AST_TYPE_SUB, // -
-                TokenType::Mul => new_node.r#type = AST_TYPE_MUL, // *
-                TokenType::Div => new_node.r#type = AST_TYPE_DIV, // /
-                TokenType::Mod => new_node.r#type = AST_TYPE_MOD, // %
-                TokenType::Equ => new_node.r#type = AST_TYPE_VAR_SET_VALUE, // =
-                TokenType::And => new_node.r#type = AST_TYPE_AND, // &
-                TokenType::Or => new_node.r#type = AST_TYPE_OR,   // |
-                TokenType::Xor => new_node.r#type = AST_TYPE_XOR, // ^
-                TokenType::LogicAnd => new_node.r#type = AST_TYPE_LOGIC_AND, // &&
-                TokenType::LogicOr => new_node.r#type = AST_TYPE_LOGIC_OR, // ||
-                TokenType::IsEqu => new_node.r#type = AST_TYPE_EQU, // ==
-                TokenType::NotEqu => new_node.r#type = AST_TYPE_NEQU, // !=
-                TokenType::LT => new_node.r#type = AST_TYPE_LT,   // <
-                TokenType::GT => new_node.r#type = AST_TYPE_GT,   // >
-                TokenType::Le => new_node.r#type = AST_TYPE_LE,   // <=
-                TokenType::Ge => new_node.r#type = AST_TYPE_GE,   // >=
-                TokenType::Shl => new_node.r#type = AST_TYPE_SHL, // <<
-                TokenType::Shr => new_node.r#type = AST_TYPE_SHR, // >>
-                TokenType::Dot => new_node.r#type = AST_TYPE_CHILD,
-                TokenType::Name => new_node.r#type = AST_TYPE_IDENTIFIER,
-                TokenType::Split => continue,
-                TokenType::RsBkt => break,
-                TokenType::RmBkt => break,
-                TokenType::RlBkt => break,
-                _ => {}
-            }
-            /* ( */
-            if token.r#type == TokenType::LsBkt {
-                new_node = AstNode::from_tokens(tokens);
-                new_node.r#type = AST_TYPE_PARAMS;
-            }
-            /* [ */
-            if token.r#type == TokenType::LmBkt {
-                new_node = AstNode::from_tokens(tokens);
-                new_node.r#type = AST_TYPE_INDEX;
-            }
-            /* { */
-            if token.r#type == TokenType::LlBkt {
-                new_node = AstNode::from_tokens(tokens);
-                new_node.r#type = AST_TYPE_CODE_BLOCK;
-            }
-            top_ast.push(Rc::new(RefCell::new(new_node)));
-        }
-        let mut node_i = 0;
-        while node_i < top_ast.nodes.len() {
-            /*
-               if expression
-               elif expression
-            */
-            if top_ast.node(node_i).r#type == AST_TYPE_IF
-                || top_ast.node(node_i).r#type == AST_TYPE_ELIF
-                || top_ast.node(node_i).r#type == AST_TYPE_WHILE
-            {
-                /* add param node */
-                let param_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(param_node);
-                top_ast.remove(node_i + 1);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    Neg,    // -
+    Not,    // !
+    BitNot, // ~
+}
 
-                /* add code block */
-                let code_block_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(code_block_node);
-                top_ast.remove(node_i + 1);
-            }
-            /* else expression */
-            if top_ast.node(node_i).r#type == AST_TYPE_ELSE {
-                /* add code block */
-                let code_block_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(code_block_node);
-                top_ast.remove(node_i + 1);
-            }
-            /* function declaration */
-            if top_ast.node(node_i).r#type == AST_TYPE_FUNC_DEF {
-                /* add identifier node */
-                let id_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(id_node);
-                top_ast.remove(node_i + 1);
+impl std::fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Self::Neg => "-",
+            Self::Not => "!",
+            Self::BitNot => "~",
+        };
+        write!(f, "{op}")
+    }
+}
 
-                /* add param node */
-                let param_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(param_node);
-                top_ast.remove(node_i + 1);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                /* function with a return type */
-                if top_ast.node(node_i + 1).data == "->" {
-                    top_ast.remove(node_i + 1); //remove "->" node
-                                                /* add code block */
-                    let code_block = Rc::clone(&top_ast.nodes[node_i + 2]);
-                    top_ast.node_mut(node_i).push(code_block);
+    #[test]
+    fn integer_radixes() {
+        assert_eq!(parse_integer("1_000"), Ok((1000, None)));
+        assert_eq!(parse_integer("0xFF"), Ok((255, None)));
+        assert_eq!(parse_integer("0xdead_beef"), Ok((0xdead_beef, None)));
+        assert_eq!(parse_integer("0o17"), Ok((15, None)));
+        assert_eq!(parse_integer("0b1010_1010"), Ok((170, None)));
+        assert_eq!(parse_integer("-0x10"), Ok((-16, None)));
+        assert_eq!(
+            parse_integer("0x"),
+            Err("'0x' is not a number.".to_string())
+        );
+        assert_eq!(
+            parse_integer("0b12"),
+            Err("'0b12' is not a number.".to_string())
+        );
+        assert_eq!(parse_integer("_"), Err("'_' is not a number.".to_string()));
+    }
 
-                    /* add ret type */
-                    let ret_type = Rc::clone(&top_ast.nodes[node_i + 1]);
-                    ret_type.borrow_mut().r#type = AST_TYPE_VAR_TYPE;
-                    top_ast.node_mut(node_i).push(ret_type);
-                    top_ast.remove(node_i + 1);
-                    top_ast.remove(node_i + 1);
-                } else {
-                    let code_block = Rc::clone(&top_ast.nodes[node_i + 1]);
-                    top_ast.node_mut(node_i).push(code_block);
-                    top_ast.remove(node_i + 1);
-                }
-            }
-            /* call a function */
-            if top_ast.node(node_i).r#type == AST_TYPE_IDENTIFIER
-                && node_i < top_ast.nodes.len() - 1
-                && top_ast.node(node_i + 1).r#type == AST_TYPE_PARAMS
-            {
-                let mut func_call_node = AstNode::new();
-                func_call_node.r#type = AST_TYPE_FUNC_CALL;
-                func_call_node.push(Rc::clone(&top_ast.nodes[node_i])); //add identifier node
-                func_call_node.push(Rc::clone(&top_ast.nodes[node_i + 1])); //add param node
-                top_ast.nodes[node_i] = Rc::new(RefCell::new(func_call_node));
-                top_ast.remove(node_i + 1);
-            }
-            /* declare a variable */
-            if top_ast.node(node_i).r#type == AST_TYPE_VAR_DECLARE {
-                /* add identifier node */
-                let id_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(id_node);
-                top_ast.remove(node_i + 1);
-            }
-            /* index an array */
-            if top_ast.node(node_i).r#type == AST_TYPE_INDEX {
-                let index = Rc::clone(&top_ast.node(node_i).nodes[0]);
-                let array = Rc::clone(&top_ast.nodes[node_i - 1]);
+    #[test]
+    fn integer_suffixes() {
+        assert_eq!(parse_integer("255u8"), Ok((255, Some(VariableType::Uint8))));
+        assert_eq!(
+            parse_integer("0xFFu16"),
+            Ok((255, Some(VariableType::Uint16)))
+        );
+        assert_eq!(
+            parse_integer("1_000i32"),
+            Ok((1000, Some(VariableType::Int32)))
+        );
+        assert_eq!(parse_integer("-5i32"), Ok((-5, Some(VariableType::Int32))));
+        assert_eq!(
+            parse_integer("-128i8"),
+            Ok((-128, Some(VariableType::Int8)))
+        );
+        assert_eq!(
+            parse_integer("-129i8"),
+            Err("integer literal '-129i8' is out of range for i8".to_string())
+        );
+        assert_eq!(
+            parse_integer("256u8"),
+            Err("integer literal '256u8' is out of range for u8".to_string())
+        );
+        assert_eq!(
+            parse_integer("-1u64"),
+            Err("integer literal '-1u64' is out of range for u64".to_string())
+        );
+    }
 
-                top_ast.node_mut(node_i).nodes.pop();
-                top_ast.node_mut(node_i).push(array);
-                top_ast.node_mut(node_i).push(index);
-                top_ast.remove(node_i - 1);
-                node_i -= 1;
-            }
-            if top_ast.node(node_i).data == ":" {
-                let type_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                type_node.borrow_mut().r#type = AST_TYPE_VAR_TYPE;
-                top_ast.node_mut(node_i - 1).push(type_node);
-                top_ast.remove(node_i);
-                top_ast.remove(node_i);
-                node_i -= 1;
-            }
-            node_i += 1;
-        }
-        /* priority is from high to low */
-        merge_op(&mut top_ast, |ast| {
-            ast.borrow().r#type == AST_TYPE_EQU
-                || ast.borrow().r#type == AST_TYPE_NEQU
-                || ast.borrow().r#type == AST_TYPE_LT
-                || ast.borrow().r#type == AST_TYPE_GT
-                || ast.borrow().r#type == AST_TYPE_LE
-                || ast.borrow().r#type == AST_TYPE_GE
-        });
-        merge_op(&mut top_ast, |ast| {
-            ast.borrow().r#type == AST_TYPE_LOGIC_AND || ast.borrow().r#type == AST_TYPE_LOGIC_OR
-        });
-        merge_op(&mut top_ast, |ast| {
-            ast.borrow().r#type == AST_TYPE_MUL
-                || ast.borrow().r#type == AST_TYPE_DIV
-                || ast.borrow().r#type == AST_TYPE_MOD
-        });
-        merge_op(&mut top_ast, |ast| {
-            ast.borrow().r#type == AST_TYPE_ADD || ast.borrow().r#type == AST_TYPE_SUB
-        });
-        merge_op(&mut top_ast, |ast| {
-            ast.borrow().r#type == AST_TYPE_SHL || ast.borrow().r#type == AST_TYPE_SHR
-        });
-        merge_op(&mut top_ast, |ast| ast.borrow().r#type == AST_TYPE_AND);
-        merge_op(&mut top_ast, |ast| ast.borrow().r#type == AST_TYPE_XOR);
-        merge_op(&mut top_ast, |ast| ast.borrow().r#type == AST_TYPE_OR);
-        /* handle 'return' '=' node */
-        let mut node_i = 0;
-        while node_i < top_ast.nodes.len() {
-            if top_ast.node(node_i).r#type == AST_TYPE_RETURN {
-                let this_node = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(this_node);
-                top_ast.remove(node_i + 1);
-            }
-            if top_ast.node(node_i).r#type == AST_TYPE_VAR_SET_VALUE {
-                let left = Rc::clone(&top_ast.nodes[node_i - 1]);
-                let right = Rc::clone(&top_ast.nodes[node_i + 1]);
-                top_ast.node_mut(node_i).push(left);
-                top_ast.node_mut(node_i).push(right);
-                /* remove left and right */
-                top_ast.remove(node_i - 1);
-                top_ast.remove(node_i);
-                node_i -= 1;
-            }
-            if top_ast.node(node_i).r#type == AST_TYPE_CHILD {
-                if top_ast.node(node_i - 1).r#type == AST_TYPE_CHILD {
-                    top_ast
-                        .node_mut(node_i)
-                        .push(Rc::clone(&top_ast.nodes[node_i - 1].borrow().nodes[1]));
-                    top_ast
-                        .node_mut(node_i)
-                        .push(Rc::clone(&top_ast.nodes[node_i + 1]));
-                    top_ast.node_mut(node_i - 1).nodes.pop();
-                    top_ast
-                        .node_mut(node_i - 1)
-                        .push(Rc::clone(&top_ast.nodes[node_i]));
-                    top_ast.nodes.remove(node_i);
-                    top_ast.nodes.remove(node_i);
-                } else {
-                    top_ast
-                        .node_mut(node_i)
-                        .push(Rc::clone(&top_ast.nodes[node_i - 1]));
-                    top_ast
-                        .node_mut(node_i)
-                        .push(Rc::clone(&top_ast.nodes[node_i + 1]));
-                    top_ast.nodes.remove(node_i + 1);
-                    top_ast.nodes.remove(node_i - 1);
-                    node_i -= 1;
-                }
-            }
-            node_i += 1;
-        }
-        top_ast
+    #[test]
+    fn integer_limits() {
+        assert_eq!(
+            parse_integer("18446744073709551615"),
+            Ok((u64::MAX as i128, None))
+        );
+        assert_eq!(
+            parse_integer("18446744073709551616"),
+            Err("integer literal '18446744073709551616' is too large".to_string())
+        );
+        assert_eq!(
+            parse_integer("0x1_0000_0000_0000_0000"),
+            Err("integer literal '0x1_0000_0000_0000_0000' is too large".to_string())
+        );
     }
 }
-
-pub const AST_TYPE_UNDEFINED: u8 = 0;
-pub const AST_TYPE_PROGRAM: u8 = 1;
-pub const AST_TYPE_IDENTIFIER: u8 = 2;
-pub const AST_TYPE_VAR_DECLARE: u8 = 3;
-pub const AST_TYPE_VAR_TYPE: u8 = 4;
-pub const AST_TYPE_VAR_SET_VALUE: u8 = 5;
-pub const AST_TYPE_FUNC_DEF: u8 = 6;
-pub const AST_TYPE_FUNC_CALL: u8 = 7;
-pub const AST_TYPE_CODE_BLOCK: u8 = 8;
-pub const AST_TYPE_PARAMS: u8 = 9;
-pub const AST_TYPE_IF: u8 = 10;
-pub const AST_TYPE_ELIF: u8 = 11;
-pub const AST_TYPE_ELSE: u8 = 12;
-pub const AST_TYPE_FOR: u8 = 13;
-pub const AST_TYPE_WHILE: u8 = 14;
-pub const AST_TYPE_ADD: u8 = 15; // +
-pub const AST_TYPE_SUB: u8 = 16; // -
-pub const AST_TYPE_MUL: u8 = 17; // *
-pub const AST_TYPE_DIV: u8 = 18; // /
-pub const AST_TYPE_MOD: u8 = 19; // %
-pub const AST_TYPE_GT: u8 = 20; // >
-pub const AST_TYPE_LT: u8 = 21; // <
-pub const AST_TYPE_GE: u8 = 22; // >=
-pub const AST_TYPE_LE: u8 = 23; // >=
-pub const AST_TYPE_EQU: u8 = 24; // ==
-pub const AST_TYPE_NEQU: u8 = 25; // !=
-pub const AST_TYPE_SHL: u8 = 26; // <<
-pub const AST_TYPE_SHR: u8 = 27; // >>
-pub const AST_TYPE_AND: u8 = 28; // &
-pub const AST_TYPE_OR: u8 = 29; // |
-pub const AST_TYPE_XOR: u8 = 30; // ^
-pub const AST_TYPE_LOGIC_AND: u8 = 31; // &&
-pub const AST_TYPE_LOGIC_OR: u8 = 32; // ||
-pub const AST_TYPE_VALUE: u8 = 33;
-pub const AST_TYPE_BREAK: u8 = 34;
-pub const AST_TYPE_CONTINUE: u8 = 35;
-pub const AST_TYPE_RETURN: u8 = 36;
-pub const AST_TYPE_INDEX: u8 = 37;
-pub const AST_TYPE_CHILD: u8 = 38;
@@ -0,0 +1,717 @@
+use crate::ast::*;
+use crate::function::Functions;
+use crate::token::Span;
+use crate::variable::VariableType;
+
+/** detect if a block returns on every path, loops are not expected to run */
+fn block_returns(block: &Block) -> bool {
+    block.stmts.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::Block(block) => block_returns(block),
+        StmtKind::If(if_stmt) => if_returns(if_stmt),
+        _ => false,
+    })
+}
+
+/** detect if every branch of an `if` returns, which needs an `else` */
+fn if_returns(if_stmt: &IfStmt) -> bool {
+    block_returns(&if_stmt.body)
+        && match &if_stmt.else_branch {
+            Some(ElseBranch::Elif(elif)) => if_returns(elif),
+            Some(ElseBranch::Else(block)) => block_returns(block),
+            None => false,
+        }
+}
+
+/** detect if an expression is made of literals without suffix, which take the type of the other operand */
+fn is_untyped(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Integer { suffix: None, .. } | Literal::Char(_)) => true,
+        ExprKind::Unary {
+            op: UnaryOp::Neg | UnaryOp::BitNot,
+            operand,
+        } => is_untyped(operand),
+        ExprKind::Binary { op, left, right } if !op.is_comparison() => {
+            !matches!(op, BinaryOp::LogicAnd | BinaryOp::LogicOr)
+                && is_untyped(left)
+                && is_untyped(right)
+        }
+        _ => false,
+    }
+}
+
+/** check if a binary operator can be applied to a type */
+fn check_operator(op: BinaryOp, var_type: &VariableType, span: Span) -> Result<(), String> {
+    let valid = match var_type {
+        VariableType::Unkown => true,
+        VariableType::Bool => matches!(
+            op,
+            BinaryOp::Equ
+                | BinaryOp::NotEqu
+                | BinaryOp::And
+                | BinaryOp::Or
+                | BinaryOp::Xor
+                | BinaryOp::LogicAnd
+                | BinaryOp::LogicOr
+        ),
+        _ => !matches!(op, BinaryOp::LogicAnd | BinaryOp::LogicOr),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(span.error(&format!("cannot apply '{op}' to {var_type}")))
+    }
+}
+
+/**
+ * Semantic analysis between parsing and code generation
+ *
+ * It resolves type names and checks operands, assignments, conditions, calls and returns.
+ * The type of every expression is saved to `Expr::r#type` for the compiler.
+ *
+ * Example:
+ * ```ignore
+ * Checker::default().check(&mut ast)?;
+ * ```
+ */
+#[derive(Default, Debug)]
+pub struct Checker {
+    functions: Functions,
+    /** variables of the enclosing blocks, the innermost one is the last */
+    scopes: Vec<Vec<(String, VariableType)>>,
+    /** return type of the function being checked */
+    ret_type: Option<VariableType>,
+    errors: Vec<String>,
+}
+
+impl Checker {
+    /** check a program, returns every error found */
+    pub fn check(&mut self, ast: &mut Ast) -> Result<(), Vec<String>> {
+        /* collect signatures first, so functions can be called before they are defined */
+        self.scopes.push(Vec::new());
+        for item in &ast.items {
+            match item {
+                Item::Func(func_def) => self.declare_func(func_def),
+                /* declared anyway, so every use of it isn't reported as undefined */
+                Item::Global(var) => {
+                    self.errors
+                        .push(var.span.error("global variables are not supported yet"));
+                    let var_type = match &var.var_type {
+                        Some(var_type) => self.resolve(var_type),
+                        None => VariableType::Unkown,
+                    };
+                    if let Err(e) = self.declare(&var.name, var_type) {
+                        self.errors.push(e);
+                    }
+                }
+            }
+        }
+        for item in &mut ast.items {
+            if let Item::Func(func_def) = item {
+                self.check_func_def(func_def);
+            }
+        }
+        self.scopes.pop();
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /** resolve a type name, an unknown type is reported and checked as `Unkown` */
+    fn resolve(&mut self, name: &Ident) -> VariableType {
+        let var_type = VariableType::from_string(&name.name);
+        if var_type == VariableType::Unkown {
+            self.errors
+                .push(name.span.error(&format!("unknown type '{}'", name.name)));
+        }
+        var_type
+    }
+
+    fn lookup(&self, name: &str) -> Option<VariableType> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(var_name, _)| var_name == name)
+            .map(|(_, var_type)| var_type.clone())
+    }
+
+    /** add a variable to the innermost scope, a name can't be declared twice in a function like `LocalVariables::push` */
+    fn declare(&mut self, name: &Ident, var_type: VariableType) -> Result<(), String> {
+        if self.lookup(&name.name).is_some() {
+            return Err(name
+                .span
+                .error(&format!("'{}' has already defined", name.name)));
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.name.clone(), var_type));
+        }
+        Ok(())
+    }
+
+    /** add the signature of a function */
+    fn declare_func(&mut self, func_def: &FuncDef) {
+        let name = &func_def.name;
+        if self.functions.lookup(&name.name).is_some() {
+            self.errors.push(
+                name.span
+                    .error(&format!("'{}' has already defined", name.name)),
+            );
+            return;
+        }
+        let params: Vec<_> = func_def
+            .params
+            .iter()
+            .map(|param| self.resolve(&param.var_type))
+            .collect();
+        let ret_type = func_def
+            .ret_type
+            .as_ref()
+            .map(|ret_type| self.resolve(ret_type));
+        self.functions.add(&name.name, &params, ret_type);
+    }
+
+    /** type names in the signature are reported by `declare_func` */
+    fn check_func_def(&mut self, func_def: &mut FuncDef) {
+        let scope = func_def
+            .params
+            .iter()
+            .map(|param| {
+                let var_type = VariableType::from_string(&param.var_type.name);
+                (param.name.name.clone(), var_type)
+            })
+            .collect();
+        let ret_type = func_def
+            .ret_type
+            .as_ref()
+            .map(|ret_type| VariableType::from_string(&ret_type.name));
+        if let Some(ret_type) = &func_def.ret_type {
+            if !block_returns(&func_def.body) {
+                self.errors.push(ret_type.span.error(&format!(
+                    "'{}' may reach the end without returning a value of type {}",
+                    func_def.name.name, ret_type.name
+                )));
+            }
+        }
+        self.ret_type = ret_type;
+
+        self.scopes.push(scope);
+        self.check_block(&mut func_def.body);
+        self.scopes.pop();
+    }
+
+    /** check statements in a new scope, an error skips to the next statement */
+    fn check_block(&mut self, block: &mut Block) {
+        self.scopes.push(Vec::new());
+        for stmt in &mut block.stmts {
+            if let Err(e) = self.check_stmt(stmt) {
+                self.errors.push(e);
+            }
+        }
+        self.scopes.pop();
+    }
+
+    /** check the condition of a branch or a loop, the body is still checked after an error */
+    fn check_cond(&mut self, cond: &mut Expr) {
+        if let Err(e) = self.expect(cond, &VariableType::Bool) {
+            self.errors.push(e);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &mut Stmt) -> Result<(), String> {
+        match &mut stmt.kind {
+            StmtKind::Var(var) => {
+                let (var_type, result) = match (&var.var_type, &mut var.init) {
+                    (Some(var_type), init) => {
+                        let var_type = self.resolve(var_type);
+                        let result = match init {
+                            Some(init) => self.expect(init, &var_type),
+                            None => Ok(()),
+                        };
+                        (var_type, result)
+                    }
+                    /* the compiler reads the inferred type from the initial value */
+                    (None, Some(init)) => match self.infer(init, None) {
+                        Ok(var_type) => (var_type, Ok(())),
+                        Err(e) => (VariableType::Unkown, Err(e)),
+                    },
+                    (None, None) => unreachable!("the parser requires a type or an initial value"),
+                };
+                /* declare it anyway to not report every use of it */
+                self.declare(&var.name, var_type)?;
+                result
+            }
+            StmtKind::Assign { target, op, value } => {
+                let var_type = self
+                    .lookup(&target.name)
+                    .ok_or_else(|| target.span.error(&format!("'{}' undefined", target.name)))?;
+                match op {
+                    Some(op @ (BinaryOp::Shl | BinaryOp::Shr)) => {
+                        check_operator(*op, &var_type, target.span)?;
+                        let amount = self.infer(value, Some(&var_type))?;
+                        check_operator(*op, &amount, value.span)
+                    }
+                    Some(op) => {
+                        check_operator(*op, &var_type, target.span)?;
+                        self.expect(value, &var_type)
+                    }
+                    None => self.expect(value, &var_type),
+                }
+            }
+            /* the return value of a call can be dropped */
+            StmtKind::Expr(expr) => {
+                if let ExprKind::Call { name, args } = &mut expr.kind {
+                    expr.r#type = self.check_call(name, args)?;
+                } else {
+                    self.infer(expr, None)?;
+                }
+                Ok(())
+            }
+            StmtKind::If(if_stmt) => {
+                self.check_if(if_stmt);
+                Ok(())
+            }
+            StmtKind::While { cond, body, .. } => {
+                self.check_cond(cond);
+                self.check_block(body);
+                Ok(())
+            }
+            StmtKind::For(for_stmt) => {
+                self.check_for(for_stmt);
+                Ok(())
+            }
+            StmtKind::Return(value) => match (value, self.ret_type.clone()) {
+                (Some(value), Some(ret_type)) => self.expect(value, &ret_type),
+                (Some(value), None) => Err(value
+                    .span
+                    .error("unexpected return value in a function without return type")),
+                (None, Some(ret_type)) => Err(stmt
+                    .span
+                    .error(&format!("expected a return value of type {ret_type}"))),
+                (None, None) => Ok(()),
+            },
+            StmtKind::Break(_) | StmtKind::Continue(_) => Ok(()),
+            StmtKind::Block(block) => {
+                self.check_block(block);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_if(&mut self, if_stmt: &mut IfStmt) {
+        self.check_cond(&mut if_stmt.cond);
+        self.check_block(&mut if_stmt.body);
+        match &mut if_stmt.else_branch {
+            Some(ElseBranch::Elif(elif)) => self.check_if(elif),
+            Some(ElseBranch::Else(block)) => self.check_block(block),
+            None => {}
+        }
+    }
+
+    /** the loop variable or the init statement are only visible in the loop */
+    fn check_for(&mut self, for_stmt: &mut ForStmt) {
+        self.scopes.push(Vec::new());
+        match &mut for_stmt.header {
+            ForHeader::Range {
+                var,
+                var_type,
+                start,
+                end,
+                step,
+            } => {
+                let var_type = match var_type {
+                    Some(var_type) => self.resolve(var_type),
+                    None => VariableType::Uint64,
+                };
+                for value in [Some(start), Some(end), step.as_mut()]
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Err(e) = self.expect(value, &var_type) {
+                        self.errors.push(e);
+                    }
+                }
+                if let Err(e) = self.declare(var, var_type) {
+                    self.errors.push(e);
+                }
+            }
+            ForHeader::CStyle { init, cond, step } => {
+                if let Some(Err(e)) = init.as_mut().map(|init| self.check_stmt(init)) {
+                    self.errors.push(e);
+                }
+                if let Some(cond) = cond {
+                    self.check_cond(cond);
+                }
+                if let Some(Err(e)) = step.as_mut().map(|step| self.check_stmt(step)) {
+                    self.errors.push(e);
+                }
+            }
+        }
+        self.check_block(&mut for_stmt.body);
+        self.scopes.pop();
+    }
+
+    /** check the arguments of a call, returns the return type of the function */
+    fn check_call(
+        &mut self,
+        name: &Ident,
+        args: &mut [Expr],
+    ) -> Result<Option<VariableType>, String> {
+        let (params, ret_type) = match self.functions.lookup(&name.name) {
+            Some(func) => (func.params.clone(), func.ret_type.clone()),
+            None => return Err(name.span.error(&format!("'{}' undefined", &name.name))),
+        };
+        if params.len() != args.len() {
+            return Err(name.span.error(&format!(
+                "'{}' takes {} arguments but {} were supplied",
+                name.name,
+                params.len(),
+                args.len()
+            )));
+        }
+        for (arg, param_type) in args.iter_mut().zip(&params) {
+            self.expect(arg, param_type)?;
+        }
+        Ok(ret_type)
+    }
+
+    /** check an expression against the type it is used as */
+    fn expect(&mut self, expr: &mut Expr, expected: &VariableType) -> Result<(), String> {
+        let found = self.infer(expr, Some(expected))?;
+        if found != *expected && found != VariableType::Unkown && *expected != VariableType::Unkown
+        {
+            return Err(expr.span.error(&format!(
+                "mismatched types: expected {expected}, found {found}"
+            )));
+        }
+        Ok(())
+    }
+
+    /**
+     * Get the type of an expression and save it to the tree
+     *
+     * Literals without suffix take the type of `hint` if it is an integer, or `u64` (`i64` if negative).
+     */
+    fn infer(
+        &mut self,
+        expr: &mut Expr,
+        hint: Option<&VariableType>,
+    ) -> Result<VariableType, String> {
+        let span = expr.span;
+        let var_type = match &mut expr.kind {
+            ExprKind::Literal(literal) => Self::infer_literal(literal, hint, span)?,
+            ExprKind::Identifier(name) => match self.lookup(name) {
+                Some(var_type) => var_type,
+                None => return Err(span.error(&format!("'{name}' undefined"))),
+            },
+            ExprKind::Binary { op, left, right } => {
+                self.infer_binary(*op, left, right, hint, span)?
+            }
+            ExprKind::Unary { op, operand } => self.infer_unary(*op, operand, hint, span)?,
+            ExprKind::Cast { operand, target } => {
+                let var_type = self.resolve(target);
+                let from = self.infer(operand, None)?;
+                /* integers are compared to 0 instead of cast to `bool` */
+                if var_type == VariableType::Bool && from != VariableType::Bool {
+                    return Err(span.error(&format!("cannot cast {from} as bool")));
+                }
+                var_type
+            }
+            ExprKind::Call { name, args } => match self.check_call(name, args)? {
+                Some(ret_type) => ret_type,
+                None => return Err(span.error(&format!("'{}' does not return a value", name.name))),
+            },
+            ExprKind::Index { .. } => return Err(span.error("indexing is not supported yet")),
+            ExprKind::Member { .. } => return Err(span.error("members are not supported yet")),
+        };
+        expr.r#type = Some(var_type.clone());
+        Ok(var_type)
+    }
+
+    fn infer_literal(
+        literal: &Literal,
+        hint: Option<&VariableType>,
+        span: Span,
+    ) -> Result<VariableType, String> {
+        let value = match literal {
+            Literal::Integer {
+                suffix: Some(suffix),
+                ..
+            } => return Ok(suffix.clone()),
+            Literal::Integer { value, .. } => *value,
+            Literal::Char(c) => *c as i128,
+            Literal::Bool(_) => return Ok(VariableType::Bool),
+            Literal::String(_) => return Err(span.error("string literals are not supported yet")),
+        };
+        let var_type = match hint {
+            Some(VariableType::Bool) | None if value < 0 => VariableType::Int64,
+            Some(VariableType::Bool) | None => VariableType::Uint64,
+            Some(hint) => hint.clone(),
+        };
+        if !var_type.contains(value) {
+            return Err(span.error(&format!("literal '{value}' is out of range for {var_type}")));
+        }
+        Ok(var_type)
+    }
+
+    fn infer_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &mut Expr,
+        right: &mut Expr,
+        hint: Option<&VariableType>,
+        span: Span,
+    ) -> Result<VariableType, String> {
+        match op {
+            BinaryOp::LogicAnd | BinaryOp::LogicOr => {
+                self.expect(left, &VariableType::Bool)?;
+                self.expect(right, &VariableType::Bool)?;
+                Ok(VariableType::Bool)
+            }
+            /* the shift amount can be any integer */
+            BinaryOp::Shl | BinaryOp::Shr => {
+                let var_type = self.infer(left, hint)?;
+                check_operator(op, &var_type, left.span)?;
+                let amount = self.infer(right, Some(&var_type))?;
+                check_operator(op, &amount, right.span)?;
+                Ok(var_type)
+            }
+            /* both operands have the same type, literals follow the other operand */
+            _ => {
+                let hint = if op.is_comparison() { None } else { hint };
+                let var_type = if is_untyped(left) && !is_untyped(right) {
+                    let var_type = self.infer(right, hint)?;
+                    self.expect(left, &var_type)?;
+                    var_type
+                } else {
+                    let var_type = self.infer(left, hint)?;
+                    self.expect(right, &var_type)?;
+                    var_type
+                };
+                check_operator(op, &var_type, span)?;
+                if op.is_comparison() {
+                    Ok(VariableType::Bool)
+                } else {
+                    Ok(var_type)
+                }
+            }
+        }
+    }
+
+    fn infer_unary(
+        &mut self,
+        op: UnaryOp,
+        operand: &mut Expr,
+        hint: Option<&VariableType>,
+        span: Span,
+    ) -> Result<VariableType, String> {
+        match op {
+            UnaryOp::Not => {
+                self.expect(operand, &VariableType::Bool)?;
+                Ok(VariableType::Bool)
+            }
+            UnaryOp::BitNot => {
+                let var_type = self.infer(operand, hint)?;
+                if var_type == VariableType::Bool {
+                    return Err(span.error(&format!("cannot apply '{op}' to {var_type}")));
+                }
+                Ok(var_type)
+            }
+            UnaryOp::Neg => {
+                let var_type = match &operand.kind {
+                    /* the range is checked with the sign, like `-128` for `i8` */
+                    ExprKind::Literal(Literal::Integer {
+                        value,
+                        suffix: None,
+                    }) => {
+                        let var_type = match hint {
+                            Some(VariableType::Bool) | None => VariableType::Int64,
+                            Some(hint) => hint.clone(),
+                        };
+                        if !var_type.contains(-value) {
+                            return Err(span.error(&format!(
+                                "literal '-{value}' is out of range for {var_type}"
+                            )));
+                        }
+                        operand.r#type = Some(var_type.clone());
+                        var_type
+                    }
+                    _ => self.infer(operand, hint)?,
+                };
+                if !var_type.is_signed() && var_type != VariableType::Unkown {
+                    return Err(span.error(&format!("cannot apply '{op}' to {var_type}")));
+                }
+                Ok(var_type)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::generate_token;
+
+    fn check(code: &str) -> Result<Ast, Vec<String>> {
+        let mut ast = Ast::from_tokens(generate_token(code).unwrap()).unwrap();
+        Checker::default().check(&mut ast)?;
+        Ok(ast)
+    }
+
+    /** check a function body, returns the first error */
+    fn check_body(body: &str) -> Result<(), String> {
+        let code = format!(
+            "func f(var x: u8) -> bool {{ return x == 0; }}
+            func main() {{ var a: i32 = 1; var b: u64 = 2; var c: bool = true; {body} }}"
+        );
+        check(&code).map(|_| ()).map_err(|errors| errors[0].clone())
+    }
+
+    #[test]
+    fn unknown_types() {
+        let errors = check("func fib(var n: Int) -> Int { var a: str; return n; }").unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "1:17: unknown type 'Int'",
+                "1:25: unknown type 'Int'",
+                "1:38: unknown type 'str'",
+            ]
+        );
+    }
+
+    #[test]
+    fn type_errors() {
+        let error = |body: &str| check_body(body).unwrap_err();
+        assert!(error("a = a + b;").ends_with("mismatched types: expected i32, found u64"));
+        assert!(error("c += c;").ends_with("cannot apply '+' to bool"));
+        assert!(error("if a { }").ends_with("mismatched types: expected bool, found i32"));
+        assert!(error("while c && b { }").ends_with("expected bool, found u64"));
+        assert!(error("b = -b;").ends_with("cannot apply '-' to u64"));
+        assert!(error("c = f(a);").ends_with("expected u8, found i32"));
+        assert!(error("c = f(256);").ends_with("literal '256' is out of range for u8"));
+        assert!(error("return 1;")
+            .ends_with("unexpected return value in a function without return type"));
+        assert!(error("b = main();").ends_with("'main' does not return a value"));
+        assert!(error("d = 1;").ends_with("'d' undefined"));
+    }
+
+    #[test]
+    fn literals_take_the_operand_type() {
+        assert!(check_body("a = 1 - a * -2147483648; c = 3 < b && f(255);").is_ok());
+        let ast = check("func main() -> bool { var a: i8 = 1; return 1 + a == -1; }").unwrap();
+        let Item::Func(main) = &ast.items[0] else {
+            unreachable!()
+        };
+        let StmtKind::Return(Some(value)) = &main.body.stmts[1].kind else {
+            unreachable!()
+        };
+        let ExprKind::Binary { left, right, .. } = &value.kind else {
+            unreachable!()
+        };
+        assert_eq!(value.r#type, Some(VariableType::Bool));
+        assert_eq!(left.r#type, Some(VariableType::Int8));
+        assert_eq!(right.r#type, Some(VariableType::Int8));
+    }
+
+    #[test]
+    fn inferred_var_types() {
+        assert!(check_body(
+            "var d = a; var e = 5i8; var g = f(1); var h = 1; d = -d; g = !g; h = b;"
+        )
+        .is_ok());
+        let error = |body: &str| check_body(body).unwrap_err();
+        assert!(error("var d = 1u8; d = b;").ends_with("mismatched types: expected u8, found u64"));
+        assert!(
+            error("var d = f(1); d = a;").ends_with("mismatched types: expected bool, found i32")
+        );
+        assert!(error("var d: u8 = 5i8;").ends_with("mismatched types: expected u8, found i8"));
+        assert!(error("var d = main();").ends_with("'main' does not return a value"));
+    }
+
+    #[test]
+    fn redeclarations() {
+        assert!(check_body("{ var d: u8 = 1; } { var d: i8 = 2; } for d in 0..b { }").is_ok());
+        let error = |body: &str| check_body(body).unwrap_err();
+        assert!(
+            error("var d: u64 = 1; var d: u8 = 2; return d;").ends_with("'d' has already defined")
+        );
+        assert!(error("var d: u64 = 1; { var d: u8 = 2; }").ends_with("'d' has already defined"));
+        assert!(error("for a in 0..5 { }").ends_with("'a' has already defined"));
+        assert_eq!(
+            check("func f(var x: u8) { var x = 1; }").unwrap_err(),
+            ["1:25: 'x' has already defined"]
+        );
+    }
+
+    #[test]
+    fn returns() {
+        assert!(check(
+            "func f(var x: u8) -> u8 {
+                if x == 0 { return 1; } elif x == 1 { { return 2; } } else { return 3; }
+            }"
+        )
+        .is_ok());
+        assert_eq!(
+            check("func f(var x: u8) -> u8 { if x == 0 { return 1; } }").unwrap_err(),
+            ["1:22: 'f' may reach the end without returning a value of type u8"]
+        );
+        /* a loop might not run */
+        assert_eq!(
+            check("func f() -> bool { while true { return true; } }").unwrap_err(),
+            ["1:13: 'f' may reach the end without returning a value of type bool"]
+        );
+        assert_eq!(
+            check("func f(var x: u8) -> u8 { if x == 0 { return 1; } elif x == 1 { return 2; } }")
+                .unwrap_err(),
+            ["1:22: 'f' may reach the end without returning a value of type u8"]
+        );
+        let error = |body: &str| check(body).unwrap_err()[0].clone();
+        assert_eq!(
+            error("func f() -> u8 { var a: u64 = 1; return a; }"),
+            "1:41: mismatched types: expected u8, found u64"
+        );
+        assert_eq!(
+            error("func f() -> i8 { return; }"),
+            "1:18: expected a return value of type i8"
+        );
+    }
+
+    #[test]
+    fn globals() {
+        assert_eq!(
+            check("var g: u8 = 1;\nfunc main() -> u8 { g += 1; return g; }").unwrap_err(),
+            ["1:1: global variables are not supported yet"]
+        );
+    }
+
+    #[test]
+    fn forward_calls() {
+        assert!(check("func main() { g(1); } func g(var x: u8) { }").is_ok());
+        let errors = check(
+            "func main() { var a = g(1, 2); } func g(var x: u8) -> u8 { return x; } func g() { }",
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "1:77: 'g' has already defined",
+                "1:23: 'g' takes 1 arguments but 2 were supplied",
+            ]
+        );
+    }
+
+    #[test]
+    fn casts() {
+        assert!(
+            check_body("var d = b as u8; d = a as u8; a = d as i32 + 1; c = c as u8 == 1;").is_ok()
+        );
+        let error = |body: &str| check_body(body).unwrap_err();
+        assert!(error("var d: u8 = b;").ends_with("mismatched types: expected u8, found u64"));
+        assert!(error("a = b as i64;").ends_with("mismatched types: expected i32, found i64"));
+        assert!(error("c = a as bool;").ends_with("cannot cast i32 as bool"));
+        assert!(error("c = a as int;").ends_with("unknown type 'int'"));
+    }
+}
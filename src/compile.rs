@@ -1,38 +1,159 @@
 use crate::assembly::*;
 use crate::ast::*;
-use crate::function::*;
 use crate::symbol::Symbols;
 use crate::variable::*;
 use crate::vm::*;
 
 pub const NORMAL_BASE_ADDR: u64 = 0;
+/** the symbol of the code calling `main` */
+pub const ENTRY_SYMBOL: &str = "_start";
+
+/** get the VM instruction of an arithmetic or bitwise operator */
+fn arithmetic_op(op: BinaryOp) -> Option<u8> {
+    let op = match op {
+        BinaryOp::Add => VM_OP_ADD,
+        BinaryOp::Sub => VM_OP_SUB,
+        BinaryOp::Mul => VM_OP_MUL,
+        BinaryOp::Div => VM_OP_DIV,
+        BinaryOp::Mod => VM_OP_MOD,
+        BinaryOp::Shl => VM_OP_SHL,
+        BinaryOp::Shr => VM_OP_SHR,
+        BinaryOp::And => VM_OP_AND,
+        BinaryOp::Or => VM_OP_OR,
+        BinaryOp::Xor => VM_OP_XOR,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/** get the signed variant of an instruction, if it has one */
+fn signed_op(op: u8) -> u8 {
+    match op {
+        VM_OP_DIV => VM_OP_SDIV,
+        VM_OP_MOD => VM_OP_SMOD,
+        VM_OP_SHR => VM_OP_SAR,
+        VM_OP_TESTGT => VM_OP_STESTGT,
+        VM_OP_TESTLT => VM_OP_STESTLT,
+        VM_OP_TESTGE => VM_OP_STESTGE,
+        VM_OP_TESTLE => VM_OP_STESTLE,
+        _ => op,
+    }
+}
+
+/** get the instruction loading a variable, signed values are sign-extended to 64 bits */
+fn load_op(var_type: &VariableType) -> u8 {
+    match (var_type.get_size(), var_type.is_signed()) {
+        (1, true) => VM_OP_LOADS8,
+        (1, false) => VM_OP_LOAD8,
+        (2, true) => VM_OP_LOADS16,
+        (2, false) => VM_OP_LOAD16,
+        (4, true) => VM_OP_LOADS32,
+        (4, false) => VM_OP_LOAD32,
+        _ => VM_OP_LOAD64,
+    }
+}
+
+/** get the instruction storing a variable, values are truncated to the size of the type */
+fn store_op(var_type: &VariableType) -> u8 {
+    match var_type.get_size() {
+        1 => VM_OP_STORE8,
+        2 => VM_OP_STORE16,
+        4 => VM_OP_STORE32,
+        _ => VM_OP_STORE64,
+    }
+}
+
+/** get the value of a literal */
+fn literal_value(expr: &Expr, literal: &Literal) -> Result<i128, String> {
+    match literal {
+        Literal::Integer { value, .. } => Ok(*value),
+        Literal::Bool(value) => Ok(*value as i128),
+        Literal::Char(c) => Ok(*c as i128),
+        Literal::String(_) => Err(expr.span.error("string literals are not supported yet")),
+    }
+}
+
+/** Labels of an enclosing loop, which are the targets of `break` and `continue` */
+#[derive(Debug)]
+struct Loop {
+    label: Option<String>,
+    continue_id: usize,
+    break_id: usize,
+    /** stack size of local variables outside the loop body */
+    stack_size: usize,
+}
+
+/** Parts of a `while` or `for` loop */
+struct LoopParts<'a> {
+    label: Option<&'a Ident>,
+    /** the loop runs forever without a condition */
+    cond: Option<&'a Expr>,
+    body: &'a Block,
+    /** run at the end of each iteration and by `continue` */
+    step: Option<&'a Stmt>,
+    /**
+     * `(counter, end, step)` of a counted `for` loop, it stops before the step
+     * if the counter would reach the end, so the counter never overflows
+     */
+    bound: Option<(Variable, Variable, Variable)>,
+}
 
 #[derive(Default, Debug)]
 pub struct Compiler {
-    pub functions: Functions,
+    /** loops enclosing the code being compiled, the innermost one is the last */
+    loops: Vec<Loop>,
+    /** trap on arithmetic overflow instead of wrapping around */
+    pub checked: bool,
 }
 
 impl Compiler {
-    pub fn compile(
-        &mut self,
-        ast: &AstNode,
-        upper: Option<&LocalVariables>,
-        symbols: &mut Symbols,
-        base_addr: u64,
-    ) -> Result<Vec<u8>, String> {
-        self.compile_block(ast, upper, symbols, base_addr)
-    }
     /**
      * Compile AST to byte code
      *
      * Example:
-     * ```
-     * let byte_code = compile::compile(&ast, None).unwrap();
+     * ```ignore
+     * let byte_code = compiler.compile(&ast, &mut symbols, NORMAL_BASE_ADDR).unwrap();
      * ```
      */
+    pub fn compile(
+        &mut self,
+        ast: &Ast,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        for item in &ast.items {
+            match item {
+                Item::Func(func) => {
+                    byte_code.extend(self.compile_func_def(
+                        func,
+                        symbols,
+                        base_addr + byte_code.len() as u64,
+                    )?);
+                }
+                Item::Global(var) => {
+                    return Err(var.span.error("global variables are not supported yet"));
+                }
+            }
+        }
+        /*
+        _start:
+        call main
+        hal
+        */
+        let main = match symbols.lookup("main") {
+            Some(main) => main,
+            None => return Err("'main' undefined".to_string()),
+        };
+        symbols.add_external_symbol(ENTRY_SYMBOL, base_addr + byte_code.len() as u64)?;
+        byte_code.extend(assemblize(VM_OP_CALL, &[AssemblyValue::Value64(main)]));
+        byte_code.extend(assemblize(VM_OP_HAL, &[]));
+        Ok(byte_code)
+    }
+    /** compile statements in a code block */
     fn compile_block(
         &mut self,
-        ast: &AstNode,
+        block: &Block,
         upper: Option<&LocalVariables>,
         symbols: &mut Symbols,
         base_addr: u64,
@@ -40,548 +161,1266 @@ impl Compiler {
         let mut byte_code = Vec::new();
         let mut variables = LocalVariables::new();
         variables.previous = upper;
-        for node in &ast.nodes {
-            if node.borrow().r#type == AST_TYPE_VAR_DECLARE {
-                byte_code.extend(self.compile_new_var(&node.borrow(), &mut variables)?);
-            }
-            if node.borrow().is_operator() {
-                byte_code.extend(self.compile_op(&node.borrow(), &mut variables)?);
-            }
-            if node.borrow().r#type == AST_TYPE_VAR_SET_VALUE {
-                if node.borrow().node(1).r#type == AST_TYPE_VALUE {
-                    /*
-                    mov c0, [value]
-                    */
-                    byte_code.extend(assemblize(
-                        VM_OP_MOV,
-                        &[
-                            AssemblyValue::Register(VM_REG_C0),
-                            AssemblyValue::Value64(node.borrow().node(1).get_value()?),
-                        ],
-                    ));
-                }
-                if node.borrow().node(1).is_operator() {
-                    byte_code.extend(self.compile_op(&node.borrow().node(1), &mut variables)?);
-                }
-                if node.borrow().node(1).r#type == AST_TYPE_IDENTIFIER {
-                    /*
-                    mov ar, sp
-                    add ar, [offest]
-                    load c0, ar
-                    */
-                    let mut offset = 0;
-                    let var = variables.lookup(&node.borrow().node(1).data);
-                    if let Some(var) = var {
-                        offset = var.offset;
-                    }
-
-                    byte_code.extend(assemblize(
-                        VM_OP_MOV,
-                        &[
-                            AssemblyValue::Register(VM_REG_AR),
-                            AssemblyValue::Register(VM_REG_SP),
-                        ],
-                    ));
-                    /* don't add `add ar, 0` */
-                    if offset > 0 {
-                        byte_code.extend(assemblize(
-                            VM_OP_ADD,
-                            &[
-                                AssemblyValue::Register(VM_REG_AR),
-                                AssemblyValue::Value16(offset as u16),
-                            ],
-                        ));
-                    }
-                    byte_code.extend(assemblize(
-                        VM_OP_LOAD64,
-                        &[
-                            AssemblyValue::Register(VM_REG_C0),
-                            AssemblyValue::Register(VM_REG_AR),
-                        ],
-                    ));
-                }
-                /*
-                mov ar, sp
-                add ar, [offest]
-                store c0, ar
-                */
-                let offset = match variables.lookup(&node.borrow().node(0).data) {
-                    Some(var) => var.offset,
-                    None => return Err(format!("'{}' undefined", &node.borrow().node(0).data)),
-                };
-                byte_code.extend(assemblize(
-                    VM_OP_MOV,
-                    &[
-                        AssemblyValue::Register(VM_REG_AR),
-                        AssemblyValue::Register(VM_REG_SP),
-                    ],
-                ));
-                /* don't add `add ar, 0` */
-                if offset > 0 {
-                    byte_code.extend(assemblize(
-                        VM_OP_ADD,
-                        &[
-                            AssemblyValue::Register(VM_REG_AR),
-                            AssemblyValue::Value16(offset as u16),
-                        ],
-                    ));
+        for stmt in &block.stmts {
+            let addr = base_addr + byte_code.len() as u64;
+            byte_code.extend(self.compile_stmt(stmt, &mut variables, symbols, addr)?);
+        }
+        byte_code.extend(Self::compile_release(variables.size()));
+        Ok(byte_code)
+    }
+    /** compile a statement, variables declared by it are added to `variables` */
+    fn compile_stmt(
+        &mut self,
+        stmt: &Stmt,
+        variables: &mut LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        match &stmt.kind {
+            StmtKind::Var(var) => {
+                byte_code.extend(self.compile_new_var(var, variables)?);
+                if let Some(init) = &var.init {
+                    byte_code.extend(self.compile_assign(
+                        &var.name,
+                        None,
+                        init,
+                        variables,
+                        symbols,
+                        base_addr + byte_code.len() as u64,
+                    )?);
                 }
-                byte_code.extend(assemblize(
-                    VM_OP_STORE64,
-                    &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_AR),
-                    ],
-                ));
             }
-            if node.borrow().r#type == AST_TYPE_RETURN {
-                /* return a constant */
-                if node.borrow().node(0).r#type == AST_TYPE_VALUE {
-                    let val = node.borrow().node(0).get_value()?;
-                    byte_code.extend(assemblize(
-                        VM_OP_MOV,
-                        &[
-                            AssemblyValue::Register(VM_REG_C0),
-                            AssemblyValue::Value64(val),
-                        ],
-                    ));
-                }
-                byte_code.extend(assemblize(VM_OP_RET, &[]));
+            StmtKind::Assign { target, op, value } => {
+                byte_code.extend(self.compile_assign(
+                    target,
+                    *op,
+                    value,
+                    variables,
+                    symbols,
+                    base_addr + byte_code.len() as u64,
+                )?);
             }
-            if node.borrow().r#type == AST_TYPE_IF {
-                byte_code.extend(self.compile_if(
-                    &node.borrow(),
-                    Some(&variables),
+            StmtKind::Expr(expr) => {
+                byte_code.extend(self.compile_expr(
+                    expr,
+                    variables,
                     symbols,
                     base_addr + byte_code.len() as u64,
                 )?);
             }
-            if node.borrow().r#type == AST_TYPE_WHILE {
+            /*
+            [value]
+            mov sp, fp
+            pop fp
+            ret
+            */
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    byte_code.extend(self.compile_expr(
+                        value,
+                        variables,
+                        symbols,
+                        base_addr + byte_code.len() as u64,
+                    )?);
+                }
+                byte_code.extend(Self::compile_epilogue());
+            }
+            StmtKind::If(if_stmt) => {
+                byte_code.extend(self.compile_if(if_stmt, Some(variables), symbols, base_addr)?);
+            }
+            StmtKind::While { label, cond, body } => {
                 byte_code.extend(self.compile_while(
-                    &node.borrow(),
-                    Some(&variables),
+                    label.as_ref(),
+                    cond,
+                    body,
+                    Some(variables),
                     symbols,
-                    base_addr + byte_code.len() as u64,
+                    base_addr,
                 )?);
             }
-            if node.borrow().r#type == AST_TYPE_FUNC_DEF {
-                byte_code.extend(self.compile_func_def(
-                    &node.borrow(),
+            StmtKind::For(for_stmt) => {
+                byte_code.extend(self.compile_for(
+                    for_stmt,
+                    Some(variables),
                     symbols,
-                    base_addr + byte_code.len() as u64,
+                    base_addr,
                 )?);
             }
-            /* call a function */
-            if node.borrow().r#type == AST_TYPE_FUNC_CALL {
-                let func_name = node.borrow().node(0).data.clone();
-                let addr = symbols.lookup(&func_name).unwrap();
-
-                /* push args into stack */
-                for arg in &node.borrow().get_params().unwrap().nodes {
-                    byte_code.extend(assemblize(
-                        VM_OP_MOV,
-                        &[
-                            AssemblyValue::Register(VM_REG_C0),
-                            AssemblyValue::Value64(arg.borrow().data.parse().unwrap()),
-                        ],
-                    ));
-                    byte_code.extend(assemblize(
-                        VM_OP_PUSH,
-                        &[AssemblyValue::Register(VM_REG_C0)],
-                    ));
-                }
-                byte_code.extend(assemblize(VM_OP_CALL, &[AssemblyValue::Value64(addr)]));
-
-                /* release args */
-                byte_code.extend(assemblize(
-                    VM_OP_ADD,
-                    &[
-                        AssemblyValue::Register(VM_REG_SP),
-                        AssemblyValue::Value8(
-                            (VM_USIZE * self.functions.lookup(&func_name).unwrap().params.len())
-                                as u8,
-                        ),
-                    ],
+            /*
+            add sp, val16: [size of variables in the loop body]
+            jmp [break/continue label]
+            */
+            StmtKind::Break(label) | StmtKind::Continue(label) => {
+                let is_break = matches!(stmt.kind, StmtKind::Break(_));
+                let target = match label {
+                    Some(label) => match self
+                        .loops
+                        .iter()
+                        .rev()
+                        .find(|target| target.label.as_ref() == Some(&label.name))
+                    {
+                        Some(target) => target,
+                        None => {
+                            return Err(label
+                                .span
+                                .error(&format!("use of undeclared label {}", label.name)))
+                        }
+                    },
+                    None => match self.loops.last() {
+                        Some(target) => target,
+                        None if is_break => {
+                            return Err(stmt.span.error("'break' outside of a loop"))
+                        }
+                        None => return Err(stmt.span.error("'continue' outside of a loop")),
+                    },
+                };
+                byte_code.extend(Self::compile_release(
+                    variables.stack_size() - target.stack_size,
                 ));
+                let id = if is_break {
+                    target.break_id
+                } else {
+                    target.continue_id
+                };
+                symbols.internal_reference(id, base_addr + byte_code.len() as u64 + 2);
+                byte_code.extend(assemblize(VM_OP_JMP, &[AssemblyValue::Value64(0)]));
+            }
+            StmtKind::Block(inner) => {
+                byte_code.extend(self.compile_block(inner, Some(variables), symbols, base_addr)?);
             }
-        }
-
-        let mut variable_size = 0;
-        for i in &variables.variables {
-            variable_size += i.size;
-        }
-        if variable_size > 0 {
-            /* add sp, val16: variable_size */
-            byte_code.extend(assemblize(
-                VM_OP_ADD,
-                &[
-                    AssemblyValue::Register(VM_REG_SP),
-                    AssemblyValue::Value16(variable_size as u16),
-                ],
-            ));
         }
         Ok(byte_code)
     }
+    /** release stack space of local variables */
+    fn compile_release(variable_size: usize) -> Vec<u8> {
+        if variable_size == 0 {
+            return Vec::new();
+        }
+        /* add sp, val16: variable_size */
+        assemblize(
+            VM_OP_ADD,
+            &[
+                AssemblyValue::Register(VM_REG_SP),
+                AssemblyValue::Value16(variable_size as u16),
+            ],
+        )
+    }
+    /**
+     * Restore the frame of the caller and return
+     *
+     * ```text
+     * mov sp, fp
+     * pop fp
+     * ret
+     * ```
+     */
+    fn compile_epilogue() -> Vec<u8> {
+        let mut byte_code = assemblize(
+            VM_OP_MOV,
+            &[
+                AssemblyValue::Register(VM_REG_SP),
+                AssemblyValue::Register(VM_REG_FP),
+            ],
+        );
+        byte_code.extend(assemblize(VM_OP_POP, &[AssemblyValue::Register(VM_REG_FP)]));
+        byte_code.extend(assemblize(VM_OP_RET, &[]));
+        byte_code
+    }
     /** compile for func declaration */
     fn compile_func_def(
         &mut self,
-        ast: &AstNode,
+        func_def: &FuncDef,
         symbols: &mut Symbols,
         base_addr: u64,
     ) -> Result<Vec<u8>, String> {
         let mut byte_code = Vec::new();
-        let func_name = ast.node(0).data.clone();
+        let func_name = func_def.name.name.clone();
         let mut local_vars = LocalVariables::new();
-        symbols.add_external_symbol(&func_name, base_addr + byte_code.len() as u64)?;
-
-        let mut func = Function::default();
+        symbols
+            .add_external_symbol(&func_name, base_addr + byte_code.len() as u64)
+            .map_err(|e| func_def.name.span.error(&e))?;
 
-        /* get params */
-        for i in &ast.node(1).nodes {
+        /*
+        get params, args are pushed in order by the caller:
+        [fp]: frame pointer of the caller
+        [fp + 8]: return address
+        [fp + 16]: the last arg
+        ...
+        args are 64 bits in big endian, so a smaller param is at the end of its slot
+        */
+        for (i, param) in func_def.params.iter().enumerate() {
             let mut new_var = Variable::new();
-            new_var.name.clone_from(&i.borrow().node(0).data);
-            new_var.r#type = VariableType::from_string(&i.borrow().node(1).data);
-            func.params.push(new_var.r#type.clone());
-            local_vars.push(new_var)?;
+            new_var.name.clone_from(&param.name.name);
+            new_var.r#type = VariableType::from_string(&param.var_type.name);
+            let slot = VM_USIZE * (func_def.params.len() - i + 1);
+            let size = match new_var.r#type.get_size() {
+                0 => VM_USIZE,
+                size => size,
+            };
+            new_var.offset = (slot + VM_USIZE - size) as isize;
+            local_vars.push(new_var).map_err(|e| param.span.error(&e))?;
         }
-        self.functions.add(&func_name, &func.params);
-        local_vars.modify_offset(VM_USIZE as isize);
+
+        /*
+        push fp
+        mov fp, sp
+        */
+        byte_code.extend(assemblize(
+            VM_OP_PUSH,
+            &[AssemblyValue::Register(VM_REG_FP)],
+        ));
+        byte_code.extend(assemblize(
+            VM_OP_MOV,
+            &[
+                AssemblyValue::Register(VM_REG_FP),
+                AssemblyValue::Register(VM_REG_SP),
+            ],
+        ));
 
         /* compile code block */
         byte_code.extend(self.compile_block(
-            &ast.get_code_block().unwrap(),
+            &func_def.body,
             Some(&local_vars),
             symbols,
             base_addr + byte_code.len() as u64,
         )?);
+        /* functions without return type can reach the end */
+        if func_def.ret_type.is_none() {
+            byte_code.extend(Self::compile_epilogue());
+        }
         Ok(byte_code)
     }
 
-    /** compile for func declaration */
+    /** compile for a function call */
+    fn compile_call(
+        &self,
+        name: &Ident,
+        args: &[Expr],
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+
+        /*
+        [arg]
+        push c0
+        */
+        for arg in args {
+            byte_code.extend(self.compile_expr(
+                arg,
+                variables,
+                symbols,
+                base_addr + byte_code.len() as u64,
+            )?);
+            byte_code.extend(assemblize(
+                VM_OP_PUSH,
+                &[AssemblyValue::Register(VM_REG_C0)],
+            ));
+        }
+        /* the function can be defined later, its address is filled by `Symbols::link` */
+        symbols.external_reference(&name.name, base_addr + byte_code.len() as u64 + 2);
+        byte_code.extend(assemblize(VM_OP_CALL, &[AssemblyValue::Value64(0)]));
+
+        /* release args */
+        byte_code.extend(Self::compile_release(VM_USIZE * args.len()));
+        Ok(byte_code)
+    }
+
+    /** compile for while loop */
     fn compile_while(
         &mut self,
-        ast: &AstNode,
+        label: Option<&Ident>,
+        cond: &Expr,
+        body: &Block,
         upper: Option<&LocalVariables>,
         symbols: &mut Symbols,
         base_addr: u64,
     ) -> Result<Vec<u8>, String> {
-        let mut byte_code = Vec::new();
         let mut variables = LocalVariables::new();
         variables.previous = upper;
+        let parts = LoopParts {
+            label,
+            cond: Some(cond),
+            body,
+            step: None,
+            bound: None,
+        };
+        self.compile_loop(parts, &mut variables, symbols, base_addr)
+    }
 
-        let start_id = symbols.alloc_internal_symbol(base_addr);
-        byte_code.extend(self.compile_op(&ast.node(0).node(0), &mut variables)?);
-
-        /*
-        start:
-        test? c0, val8: 1
-        jne out_of_code_block
+    /** compile for `for` loops, which are lowered like while loops */
+    fn compile_for(
+        &mut self,
+        for_stmt: &ForStmt,
+        upper: Option<&LocalVariables>,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        /* the loop variable is only visible in the loop */
+        let mut variables = LocalVariables::new();
+        variables.previous = upper;
 
-        if_code_block:
-        ...
-        jmp start
-        out_of_code_block:
-        ...
-        */
-        let out_of_code_block_id = symbols.alloc_internal_symbol(0);
-        symbols.internal_reference(out_of_code_block_id, base_addr + byte_code.len() as u64 + 3);
-        byte_code.extend(assemblize(
-            VM_OP_JNE,
-            &[
-                AssemblyValue::Register(VM_REG_C0),
-                AssemblyValue::Value64(0),
-            ],
-        ));
+        let (cond, step, bound) = match &for_stmt.header {
+            /*
+            [start]
+            sub sp, val16: [var size]
+            mov ar, fp
+            sub ar, [offset]
+            store c0, ar
+            [the same for end and step, which are only evaluated once]
+            while var < end {
+                ...
+                if end - var <= step { break }
+                var += step
+            }
+            */
+            ForHeader::Range {
+                var,
+                var_type,
+                start,
+                end,
+                step,
+            } => {
+                let var_type = match var_type {
+                    Some(var_type) => var_type.clone(),
+                    None => Ident {
+                        name: VariableType::Uint64.to_string(),
+                        span: var.span,
+                    },
+                };
+                let one = Expr {
+                    kind: ExprKind::Literal(Literal::Integer {
+                        value: 1,
+                        suffix: None,
+                    }),
+                    span: var.span,
+                    r#type: None,
+                };
+                /* hidden names can't be written in the source code */
+                let hidden = [
+                    (var.clone(), start.as_ref()),
+                    (
+                        Ident {
+                            name: format!("{}.end", var.name),
+                            span: end.span,
+                        },
+                        end.as_ref(),
+                    ),
+                    (
+                        Ident {
+                            name: format!("{}.step", var.name),
+                            span: var.span,
+                        },
+                        step.as_deref().unwrap_or(&one),
+                    ),
+                ];
+                for (name, value) in &hidden {
+                    byte_code.extend(self.compile_expr(
+                        value,
+                        &variables,
+                        symbols,
+                        base_addr + byte_code.len() as u64,
+                    )?);
+                    byte_code.extend(self.compile_new_var(
+                        &VarDecl {
+                            name: name.clone(),
+                            var_type: Some(var_type.clone()),
+                            init: None,
+                            span: name.span,
+                        },
+                        &mut variables,
+                    )?);
+                    let new_var = variables.lookup(&name.name).unwrap();
+                    byte_code.extend(Self::compile_var_address(new_var.offset));
+                    byte_code.extend(assemblize(
+                        store_op(&new_var.r#type),
+                        &[
+                            AssemblyValue::Register(VM_REG_C0),
+                            AssemblyValue::Register(VM_REG_AR),
+                        ],
+                    ));
+                }
 
-        /* compile code block */
-        byte_code.extend(self.compile_block(
-            &ast.get_code_block().unwrap(),
-            upper,
+                let [counter, end, step] =
+                    hidden.map(|(name, _)| variables.lookup(&name.name).unwrap());
+                let load = |var: &Variable| Expr {
+                    kind: ExprKind::Identifier(var.name.clone()),
+                    span: var_type.span,
+                    r#type: Some(var.r#type.clone()),
+                };
+                let cond = Expr {
+                    span: var_type.span,
+                    kind: ExprKind::Binary {
+                        op: BinaryOp::LT,
+                        left: Box::new(load(&counter)),
+                        right: Box::new(load(&end)),
+                    },
+                    r#type: Some(VariableType::Bool),
+                };
+                let step_stmt = Stmt {
+                    span: var.span,
+                    kind: StmtKind::Assign {
+                        target: var.clone(),
+                        op: Some(BinaryOp::Add),
+                        value: load(&step),
+                    },
+                };
+                (Some(cond), Some(step_stmt), Some((counter, end, step)))
+            }
+            ForHeader::CStyle { init, cond, step } => {
+                if let Some(init) = init {
+                    byte_code.extend(self.compile_stmt(
+                        init,
+                        &mut variables,
+                        symbols,
+                        base_addr + byte_code.len() as u64,
+                    )?);
+                }
+                (cond.clone(), step.as_deref().cloned(), None)
+            }
+        };
+        let parts = LoopParts {
+            label: for_stmt.label.as_ref(),
+            cond: cond.as_ref(),
+            body: &for_stmt.body,
+            step: step.as_ref(),
+            bound,
+        };
+        byte_code.extend(self.compile_loop(
+            parts,
+            &mut variables,
             symbols,
             base_addr + byte_code.len() as u64,
         )?);
-        symbols.internal_reference(start_id, base_addr + byte_code.len() as u64 + 2);
-        byte_code.extend(assemblize(VM_OP_JMP, &[AssemblyValue::Value64(0)]));
-
-        symbols.modify_internal_sym(out_of_code_block_id, base_addr + byte_code.len() as u64);
+        byte_code.extend(Self::compile_release(variables.size()));
         Ok(byte_code)
     }
 
-    /** compile for if compression */
-    fn compile_if(
+    /**
+     * compile the jumps of a loop
+     * `variables` is the scope of the loop
+     */
+    fn compile_loop(
         &mut self,
-        ast: &AstNode,
-        upper: Option<&LocalVariables>,
+        LoopParts {
+            label,
+            cond,
+            body,
+            step,
+            bound,
+        }: LoopParts,
+        variables: &mut LocalVariables,
         symbols: &mut Symbols,
         base_addr: u64,
     ) -> Result<Vec<u8>, String> {
         let mut byte_code = Vec::new();
-        let mut variables = LocalVariables::new();
-        variables.previous = upper;
-        byte_code.extend(self.compile_op(&ast.node(0).node(0), &mut variables)?);
+        let start_id = symbols.alloc_internal_symbol(base_addr);
 
         /*
+        start:
         test? c0, val8: 1
-        j?e out_of_code_block
+        jne out_of_code_block
 
         if_code_block:
         ...
+        continue:
+        [step]
+        jmp start
         out_of_code_block:
         ...
         */
         let out_of_code_block_id = symbols.alloc_internal_symbol(0);
-        symbols.internal_reference(out_of_code_block_id, base_addr + byte_code.len() as u64 + 3);
-        byte_code.extend(assemblize(
-            VM_OP_JNE,
-            &[
-                AssemblyValue::Register(VM_REG_C0),
-                AssemblyValue::Value64(0),
-            ],
-        ));
-
-        /* compile code block */
+        if let Some(cond) = cond {
+            byte_code.extend(self.compile_expr(
+                cond,
+                variables,
+                symbols,
+                base_addr + byte_code.len() as u64,
+            )?);
+            symbols
+                .internal_reference(out_of_code_block_id, base_addr + byte_code.len() as u64 + 3);
+            byte_code.extend(assemblize(
+                VM_OP_JNE,
+                &[
+                    AssemblyValue::Register(VM_REG_C0),
+                    AssemblyValue::Value64(0),
+                ],
+            ));
+        }
+
+        /* `continue` jumps to the step if there is one */
+        let continue_id = match step {
+            Some(_) => symbols.alloc_internal_symbol(0),
+            None => start_id,
+        };
+        self.loops.push(Loop {
+            label: label.map(|label| label.name.clone()),
+            continue_id,
+            break_id: out_of_code_block_id,
+            stack_size: variables.stack_size(),
+        });
+        /* compile code block */
+        let body = self.compile_block(
+            body,
+            Some(variables),
+            symbols,
+            base_addr + byte_code.len() as u64,
+        );
+        self.loops.pop();
+        byte_code.extend(body?);
+        if let Some(step) = step {
+            symbols.modify_internal_sym(continue_id, base_addr + byte_code.len() as u64);
+            /*
+            [end to c0]
+            [counter to c1]
+            sub c0, c1
+            [step to c1]
+            testle c0, c0, c1
+            je c0, out_of_code_block
+            */
+            if let Some((counter, end, step)) = bound {
+                /* both sides are extended to 64 bits, so `end - counter` is exact */
+                let load = |var: &Variable, register: u8| {
+                    let mut byte_code = Self::compile_var_address(var.offset);
+                    byte_code.extend(assemblize(
+                        load_op(&var.r#type),
+                        &[
+                            AssemblyValue::Register(register),
+                            AssemblyValue::Register(VM_REG_AR),
+                        ],
+                    ));
+                    byte_code
+                };
+                byte_code.extend(load(&end, VM_REG_C0));
+                byte_code.extend(load(&counter, VM_REG_C1));
+                byte_code.extend(assemblize(
+                    VM_OP_SUB,
+                    &[
+                        AssemblyValue::Register(VM_REG_C0),
+                        AssemblyValue::Register(VM_REG_C1),
+                    ],
+                ));
+                byte_code.extend(load(&step, VM_REG_C1));
+                byte_code.extend(assemblize(
+                    VM_OP_TESTLE,
+                    &[
+                        AssemblyValue::Register(VM_REG_C0),
+                        AssemblyValue::Register(VM_REG_C0),
+                        AssemblyValue::Register(VM_REG_C1),
+                    ],
+                ));
+                symbols.internal_reference(
+                    out_of_code_block_id,
+                    base_addr + byte_code.len() as u64 + 3,
+                );
+                byte_code.extend(assemblize(
+                    VM_OP_JE,
+                    &[
+                        AssemblyValue::Register(VM_REG_C0),
+                        AssemblyValue::Value64(0),
+                    ],
+                ));
+            }
+            byte_code.extend(self.compile_stmt(
+                step,
+                variables,
+                symbols,
+                base_addr + byte_code.len() as u64,
+            )?);
+        }
+        symbols.internal_reference(start_id, base_addr + byte_code.len() as u64 + 2);
+        byte_code.extend(assemblize(VM_OP_JMP, &[AssemblyValue::Value64(0)]));
+
+        symbols.modify_internal_sym(out_of_code_block_id, base_addr + byte_code.len() as u64);
+        Ok(byte_code)
+    }
+
+    /** compile for if compression, `elif` and `else` branches are chained after the body */
+    fn compile_if(
+        &mut self,
+        if_stmt: &IfStmt,
+        upper: Option<&LocalVariables>,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        let mut variables = LocalVariables::new();
+        variables.previous = upper;
+        byte_code.extend(self.compile_expr(
+            &if_stmt.cond,
+            &variables,
+            symbols,
+            base_addr + byte_code.len() as u64,
+        )?);
+
+        /*
+        test? c0, val8: 1
+        jne else_branch
+
+        if_code_block:
+        ...
+        jmp out_of_if
+        else_branch:
+        ...
+        out_of_if:
+        ...
+        */
+        let else_branch_id = symbols.alloc_internal_symbol(0);
+        symbols.internal_reference(else_branch_id, base_addr + byte_code.len() as u64 + 3);
+        byte_code.extend(assemblize(
+            VM_OP_JNE,
+            &[
+                AssemblyValue::Register(VM_REG_C0),
+                AssemblyValue::Value64(0),
+            ],
+        ));
+
+        /* compile code block */
         byte_code.extend(self.compile_block(
-            &ast.get_code_block().unwrap(),
+            &if_stmt.body,
             upper,
             symbols,
             base_addr + byte_code.len() as u64,
         )?);
 
-        symbols.modify_internal_sym(out_of_code_block_id, base_addr + byte_code.len() as u64);
+        let else_branch = match &if_stmt.else_branch {
+            Some(else_branch) => else_branch,
+            None => {
+                symbols.modify_internal_sym(else_branch_id, base_addr + byte_code.len() as u64);
+                return Ok(byte_code);
+            }
+        };
+        let out_of_if_id = symbols.alloc_internal_symbol(0);
+        symbols.internal_reference(out_of_if_id, base_addr + byte_code.len() as u64 + 2);
+        byte_code.extend(assemblize(VM_OP_JMP, &[AssemblyValue::Value64(0)]));
+
+        symbols.modify_internal_sym(else_branch_id, base_addr + byte_code.len() as u64);
+        let addr = base_addr + byte_code.len() as u64;
+        match else_branch {
+            /* an `elif` is an `if` nested in the else branch */
+            ElseBranch::Elif(elif) => {
+                byte_code.extend(self.compile_if(elif, upper, symbols, addr)?);
+            }
+            ElseBranch::Else(block) => {
+                byte_code.extend(self.compile_block(block, upper, symbols, addr)?);
+            }
+        }
+        symbols.modify_internal_sym(out_of_if_id, base_addr + byte_code.len() as u64);
         Ok(byte_code)
     }
 
-    /**
-     * compile for operating tree  
-     * **NOTE**: The result will be saved to C0
-     */
-    fn compile_op(&self, ast: &AstNode, variables: &mut LocalVariables) -> Result<Vec<u8>, String> {
+    /** compile for `target = value` and `target [op]= value` */
+    fn compile_assign(
+        &self,
+        target: &Ident,
+        op: Option<BinaryOp>,
+        value: &Expr,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
         let mut byte_code = Vec::new();
-        /* left value */
-        if ast.node(0).r#type == AST_TYPE_VALUE {
-            /*
-            mov c0, val
-            */
-            let val = ast.node(0).get_value()?;
+        let var = match variables.lookup(&target.name) {
+            Some(var) => var,
+            None => return Err(target.span.error(&format!("'{}' undefined", &target.name))),
+        };
+        byte_code.extend(self.compile_expr(
+            value,
+            variables,
+            symbols,
+            base_addr + byte_code.len() as u64,
+        )?);
+        /*
+        mov c1, c0
+        mov ar, fp
+        add/sub ar, [offset]
+        load[size] c0, ar
+        [op] c0, c1
+        [wrap or check c0]
+        */
+        if let Some(op) = op {
             byte_code.extend(assemblize(
                 VM_OP_MOV,
                 &[
+                    AssemblyValue::Register(VM_REG_C1),
                     AssemblyValue::Register(VM_REG_C0),
-                    AssemblyValue::Value64(val),
-                ],
-            ));
-        }
-        /* variable */
-        else if ast.node(0).r#type == AST_TYPE_IDENTIFIER {
-            let offset = match variables.lookup(&ast.node(0).data) {
-                Some(var) => var.offset,
-                None => return Err(format!("'{}' undefined.", &ast.node(0).data)),
-            };
-
-            /*
-            mov ar, sp
-            add ar, val16: offset
-            load c0, ar
-            */
-            byte_code.extend(assemblize(
-                VM_OP_MOV,
-                &[
-                    AssemblyValue::Register(VM_REG_AR),
-                    AssemblyValue::Register(VM_REG_SP),
                 ],
             ));
-
+            byte_code.extend(Self::compile_var_address(var.offset));
             byte_code.extend(assemblize(
-                VM_OP_ADD,
+                load_op(&var.r#type),
                 &[
+                    AssemblyValue::Register(VM_REG_C0),
                     AssemblyValue::Register(VM_REG_AR),
-                    AssemblyValue::Value16(offset as u16),
                 ],
             ));
-
+            let op = arithmetic_op(op).unwrap();
+            let op = if var.r#type.is_signed() {
+                signed_op(op)
+            } else {
+                op
+            };
             byte_code.extend(assemblize(
-                VM_OP_LOAD64,
+                op,
                 &[
                     AssemblyValue::Register(VM_REG_C0),
-                    AssemblyValue::Register(VM_REG_AR),
+                    AssemblyValue::Register(VM_REG_C1),
                 ],
             ));
+            byte_code.extend(self.compile_overflow(&Some(var.r#type.clone()), VM_REG_C0));
         }
-        /* operating result */
-        else if ast.node(0).is_operator() {
-            byte_code.extend(self.compile_op(&ast.node(0), variables)?);
+        /*
+        mov ar, fp
+        add/sub ar, [offset]
+        store[size] c0, ar
+        */
+        byte_code.extend(Self::compile_var_address(var.offset));
+        byte_code.extend(assemblize(
+            store_op(&var.r#type),
+            &[
+                AssemblyValue::Register(VM_REG_C0),
+                AssemblyValue::Register(VM_REG_AR),
+            ],
+        ));
+        Ok(byte_code)
+    }
+
+    /**
+     * compile for an expression
+     * **NOTE**: The result will be saved to C0
+     */
+    fn compile_expr(
+        &self,
+        expr: &Expr,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        self.compile_expr_to(expr, VM_REG_C0, variables, symbols, base_addr)
+    }
+
+    /**
+     * Compile an expression into `register`, one of C0 to C3
+     *
+     * Registers below `register` hold live values and are kept,
+     * registers above it are free to be used by sub-trees.
+     */
+    fn compile_expr_to(
+        &self,
+        expr: &Expr,
+        register: u8,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        match &expr.kind {
+            ExprKind::Literal(_) | ExprKind::Identifier(_) => {
+                self.compile_load(expr, register, variables)
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOp::LogicAnd | BinaryOp::LogicOr),
+                left,
+                right,
+            } => self.compile_logic(*op, left, right, register, variables, symbols, base_addr),
+            ExprKind::Binary { op, left, right } => {
+                self.compile_op(*op, left, right, register, variables, symbols, base_addr)
+            }
+            ExprKind::Call { name, args } => {
+                self.compile_call_to(name, args, register, variables, symbols, base_addr)
+            }
+            ExprKind::Unary { op, operand } => {
+                self.compile_unary(*op, operand, register, variables, symbols, base_addr)
+            }
+            ExprKind::Cast { operand, target } => {
+                let mut byte_code =
+                    self.compile_expr_to(operand, register, variables, symbols, base_addr)?;
+                let target = VariableType::from_string(&target.name);
+                if let Some(from) = &operand.r#type {
+                    byte_code.extend(Self::compile_cast(from, &target, register));
+                }
+                Ok(byte_code)
+            }
+            ExprKind::Index { .. } => Err(expr.span.error("indexing is not supported yet")),
+            ExprKind::Member { .. } => Err(expr.span.error("members are not supported yet")),
         }
+    }
 
-        /* right value */
-        /* constant */
-        if ast.node(1).r#type == AST_TYPE_VALUE {
-            let val: u64 = ast.node(1).get_value()?;
-            /* mov c1, val64: val */
+    /**
+     * compile for a call whose return value is moved to `register`
+     *
+     * ```text
+     * push c0
+     * ...
+     * push [register - 1]
+     * [call]
+     * mov [register], c0
+     * pop [register - 1]
+     * ...
+     * pop c0
+     * ```
+     */
+    fn compile_call_to(
+        &self,
+        name: &Ident,
+        args: &[Expr],
+        register: u8,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        /* save live registers, which may be changed by the callee */
+        for live in VM_REG_C0..register {
+            byte_code.extend(assemblize(VM_OP_PUSH, &[AssemblyValue::Register(live)]));
+        }
+        byte_code.extend(self.compile_call(
+            name,
+            args,
+            variables,
+            symbols,
+            base_addr + byte_code.len() as u64,
+        )?);
+        if register != VM_REG_C0 {
             byte_code.extend(assemblize(
                 VM_OP_MOV,
                 &[
-                    AssemblyValue::Register(VM_REG_C1),
-                    AssemblyValue::Value64(val),
+                    AssemblyValue::Register(register),
+                    AssemblyValue::Register(VM_REG_C0),
                 ],
             ));
         }
-        /* variable */
-        else if ast.node(1).r#type == AST_TYPE_IDENTIFIER {
-            let offset = match variables.lookup(&ast.node(1).data) {
-                Some(var) => var.offset,
-                None => return Err(format!("'{}' undefined.", &ast.node(1).data)),
-            };
+        for live in (VM_REG_C0..register).rev() {
+            byte_code.extend(assemblize(VM_OP_POP, &[AssemblyValue::Register(live)]));
+        }
+        Ok(byte_code)
+    }
 
+    /** load a literal or a variable into a register */
+    fn compile_load(
+        &self,
+        expr: &Expr,
+        register: u8,
+        variables: &LocalVariables,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        match &expr.kind {
             /*
-            mov ar, sp
-            add ar, [offset]
-            load ar, c3
+            mov [register], val
             */
-            byte_code.extend(assemblize(
-                VM_OP_MOV,
-                &[
-                    AssemblyValue::Register(VM_REG_AR),
-                    AssemblyValue::Register(VM_REG_SP),
-                ],
-            ));
+            ExprKind::Literal(literal) => {
+                let val = literal_value(expr, literal)? as u64;
+                byte_code.extend(assemblize(
+                    VM_OP_MOV,
+                    &[
+                        AssemblyValue::Register(register),
+                        AssemblyValue::Value64(val),
+                    ],
+                ));
+            }
+            /*
+            mov ar, fp
+            add/sub ar, val16: offset
+            load[size] [register], ar
+            */
+            ExprKind::Identifier(name) => {
+                let var = match variables.lookup(name) {
+                    Some(var) => var,
+                    None => return Err(expr.span.error(&format!("'{name}' undefined."))),
+                };
+                byte_code.extend(Self::compile_var_address(var.offset));
+                byte_code.extend(assemblize(
+                    load_op(&var.r#type),
+                    &[
+                        AssemblyValue::Register(register),
+                        AssemblyValue::Register(VM_REG_AR),
+                    ],
+                ));
+            }
+            _ => unreachable!("only literals and variables can be loaded directly"),
+        }
+        Ok(byte_code)
+    }
 
-            byte_code.extend(assemblize(
-                VM_OP_ADD,
-                &[
-                    AssemblyValue::Register(VM_REG_AR),
-                    AssemblyValue::Value16(offset as u16),
-                ],
-            ));
+    /**
+     * compile for `&&` and `||`, the right side only runs if the left one does not decide the result
+     * **NOTE**: The result will be saved to `register` as 0 or 1
+     *
+     * ```text
+     * [left value]
+     * testneq [register], [register], val8: 0
+     * jne/je [register], out_of_logic
+     * [right value]
+     * testneq [register], [register], val8: 0
+     * out_of_logic:
+     * ```
+     */
+    #[allow(clippy::too_many_arguments)]
+    fn compile_logic(
+        &self,
+        op: BinaryOp,
+        left: &Expr,
+        right: &Expr,
+        register: u8,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        /* 0 is false and any other value is true */
+        let test = assemblize(
+            VM_OP_TESTNEQ,
+            &[
+                AssemblyValue::Register(register),
+                AssemblyValue::Register(register),
+                AssemblyValue::Value8(0),
+            ],
+        );
+        byte_code.extend(self.compile_expr_to(left, register, variables, symbols, base_addr)?);
+        byte_code.extend(&test);
+
+        /* a false left side of `&&` and a true one of `||` is the result */
+        let jump = match op {
+            BinaryOp::LogicAnd => VM_OP_JNE,
+            _ => VM_OP_JE,
+        };
+        let out_of_logic_id = symbols.alloc_internal_symbol(0);
+        symbols.internal_reference(out_of_logic_id, base_addr + byte_code.len() as u64 + 3);
+        byte_code.extend(assemblize(
+            jump,
+            &[AssemblyValue::Register(register), AssemblyValue::Value64(0)],
+        ));
+
+        byte_code.extend(self.compile_expr_to(
+            right,
+            register,
+            variables,
+            symbols,
+            base_addr + byte_code.len() as u64,
+        )?);
+        byte_code.extend(&test);
+        symbols.modify_internal_sym(out_of_logic_id, base_addr + byte_code.len() as u64);
+        Ok(byte_code)
+    }
+
+    /**
+     * compile for operating tree
+     * **NOTE**: The result will be saved to `register`
+     */
+    #[allow(clippy::too_many_arguments)]
+    fn compile_op(
+        &self,
+        op: BinaryOp,
+        left: &Expr,
+        right: &Expr,
+        register: u8,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code = Vec::new();
+        /* both operands have the type of the result, except the amount of a shift */
+        let var_type = left.r#type.clone();
+        let signed = var_type
+            .as_ref()
+            .is_some_and(|var_type| var_type.is_signed());
+        /* left value */
+        byte_code.extend(self.compile_expr_to(
+            left,
+            register,
+            variables,
+            symbols,
+            base_addr + byte_code.len() as u64,
+        )?);
 
+        /* right value, in the next register if there is one */
+        let right_register = if register < VM_REG_C3 {
+            byte_code.extend(self.compile_expr_to(
+                right,
+                register + 1,
+                variables,
+                symbols,
+                base_addr + byte_code.len() as u64,
+            )?);
+            register + 1
+        }
+        /* all registers are used, a leaf can still be loaded into AR */
+        else if matches!(right.kind, ExprKind::Literal(_) | ExprKind::Identifier(_)) {
+            byte_code.extend(self.compile_load(right, VM_REG_AR, variables)?);
+            VM_REG_AR
+        }
+        /*
+        spill the left value to the stack:
+        push c3
+        [right value to c3]
+        mov ar, c3
+        pop c3
+        */
+        else {
+            byte_code.extend(assemblize(VM_OP_PUSH, &[AssemblyValue::Register(register)]));
+            byte_code.extend(self.compile_expr_to(
+                right,
+                register,
+                variables,
+                symbols,
+                base_addr + byte_code.len() as u64,
+            )?);
             byte_code.extend(assemblize(
-                VM_OP_LOAD64,
+                VM_OP_MOV,
                 &[
-                    AssemblyValue::Register(VM_REG_C1),
                     AssemblyValue::Register(VM_REG_AR),
+                    AssemblyValue::Register(register),
                 ],
             ));
-        }
-        /* operating result */
-        else if ast.node(1).is_operator() {
-            /* push c0 */
-            byte_code.extend(assemblize(
-                VM_OP_PUSH,
-                &[AssemblyValue::Register(VM_REG_C0)],
-            ));
-            byte_code.extend(self.compile_op(&ast.node(1), variables)?);
-            /* mov c1, c0 */
+            byte_code.extend(assemblize(VM_OP_POP, &[AssemblyValue::Register(register)]));
+            VM_REG_AR
+        };
+
+        /* [add/sub/mul/div] [register], [right register] */
+        if let Some(op) = arithmetic_op(op) {
+            let op = if signed { signed_op(op) } else { op };
             byte_code.extend(assemblize(
-                VM_OP_MOV,
+                op,
                 &[
-                    AssemblyValue::Register(VM_REG_C1),
-                    AssemblyValue::Register(VM_REG_C0),
+                    AssemblyValue::Register(register),
+                    AssemblyValue::Register(right_register),
                 ],
             ));
-            /* pop c0 */
-            byte_code.extend(assemblize(VM_OP_POP, &[AssemblyValue::Register(VM_REG_C0)]));
-        }
-        /* [add/sub/mul/div] c0, c1 */
-        let op = match ast.r#type {
-            AST_TYPE_ADD => VM_OP_ADD,
-            AST_TYPE_SUB => VM_OP_SUB,
-            AST_TYPE_MUL => VM_OP_MUL,
-            AST_TYPE_DIV => VM_OP_DIV,
-            AST_TYPE_AND => VM_OP_AND,
-            AST_TYPE_OR => VM_OP_OR,
-            AST_TYPE_XOR => VM_OP_XOR,
-            AST_TYPE_LOGIC_AND => VM_OP_AND,
-            AST_TYPE_LOGIC_OR => VM_OP_OR,
-            AST_TYPE_MOD => VM_OP_MOD,
-            AST_TYPE_SHL => VM_OP_SHL,
-            AST_TYPE_SHR => VM_OP_SHR,
-            AST_TYPE_EQU => {
-                byte_code.extend(assemblize(
-                    VM_OP_TESTEQ,
-                    &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
-                    ],
-                ));
-                return Ok(byte_code);
+            /* bitwise operations and right shifts never leave the type */
+            if matches!(
+                op,
+                VM_OP_ADD
+                    | VM_OP_SUB
+                    | VM_OP_MUL
+                    | VM_OP_DIV
+                    | VM_OP_MOD
+                    | VM_OP_SDIV
+                    | VM_OP_SMOD
+                    | VM_OP_SHL
+            ) {
+                byte_code.extend(self.compile_overflow(&var_type, register));
             }
-            AST_TYPE_NEQU => {
+            return Ok(byte_code);
+        }
+        /* test? [register], [register], [right register] */
+        let test = match op {
+            BinaryOp::Equ => VM_OP_TESTEQ,
+            BinaryOp::NotEqu => VM_OP_TESTNEQ,
+            BinaryOp::GT => VM_OP_TESTGT,
+            BinaryOp::LT => VM_OP_TESTLT,
+            BinaryOp::Ge => VM_OP_TESTGE,
+            _ => VM_OP_TESTLE,
+        };
+        let test = if signed { signed_op(test) } else { test };
+        byte_code.extend(assemblize(
+            test,
+            &[
+                AssemblyValue::Register(register),
+                AssemblyValue::Register(register),
+                AssemblyValue::Register(right_register),
+            ],
+        ));
+        Ok(byte_code)
+    }
+
+    /**
+     * compile for prefix operators
+     * **NOTE**: The result will be saved to `register`
+     */
+    fn compile_unary(
+        &self,
+        op: UnaryOp,
+        operand: &Expr,
+        register: u8,
+        variables: &LocalVariables,
+        symbols: &mut Symbols,
+        base_addr: u64,
+    ) -> Result<Vec<u8>, String> {
+        let mut byte_code =
+            self.compile_expr_to(operand, register, variables, symbols, base_addr)?;
+        match op {
+            /*
+            mov ar, [register]
+            mov [register], val8: 0
+            sub [register], ar
+            */
+            UnaryOp::Neg => {
                 byte_code.extend(assemblize(
-                    VM_OP_TESTNEQ,
+                    VM_OP_MOV,
                     &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
+                        AssemblyValue::Register(VM_REG_AR),
+                        AssemblyValue::Register(register),
                     ],
                 ));
-                return Ok(byte_code);
-            }
-            AST_TYPE_GT => {
                 byte_code.extend(assemblize(
-                    VM_OP_TESTGT,
-                    &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
-                    ],
+                    VM_OP_MOV,
+                    &[AssemblyValue::Register(register), AssemblyValue::Value8(0)],
                 ));
-                return Ok(byte_code);
-            }
-            AST_TYPE_LT => {
                 byte_code.extend(assemblize(
-                    VM_OP_TESTLT,
+                    VM_OP_SUB,
                     &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
+                        AssemblyValue::Register(register),
+                        AssemblyValue::Register(VM_REG_AR),
                     ],
                 ));
-                return Ok(byte_code);
+                byte_code.extend(self.compile_overflow(&operand.r#type, register));
             }
-            AST_TYPE_GE => {
+            /* testeq [register], [register], val8: 0 */
+            UnaryOp::Not => {
                 byte_code.extend(assemblize(
-                    VM_OP_TESTLE,
+                    VM_OP_TESTEQ,
                     &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
+                        AssemblyValue::Register(register),
+                        AssemblyValue::Register(register),
+                        AssemblyValue::Value8(0),
                     ],
                 ));
-                return Ok(byte_code);
             }
-            AST_TYPE_LE => {
-                byte_code.extend(assemblize(
-                    VM_OP_TESTLE,
-                    &[
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C0),
-                        AssemblyValue::Register(VM_REG_C1),
-                    ],
-                ));
-                return Ok(byte_code);
+            /* not [register] */
+            UnaryOp::BitNot => {
+                byte_code.extend(assemblize(VM_OP_NOT, &[AssemblyValue::Register(register)]));
+                if let Some(var_type) = &operand.r#type {
+                    byte_code.extend(Self::compile_wrap(var_type, register));
+                }
             }
-            _ => return Ok(byte_code), // this will be never executed
+        }
+        Ok(byte_code)
+    }
+
+    /**
+     * Handle the result of an arithmetic operation on `var_type` overflowing,
+     * it traps in checked mode and wraps around otherwise
+     *
+     * ```text
+     * chku/chks [register], [bits]
+     * ```
+     */
+    fn compile_overflow(&self, var_type: &Option<VariableType>, register: u8) -> Vec<u8> {
+        let var_type = match var_type {
+            Some(VariableType::Bool | VariableType::Unkown) | None => return Vec::new(),
+            Some(var_type) => var_type,
+        };
+        if !self.checked {
+            return Self::compile_wrap(var_type, register);
+        }
+        let op = if var_type.is_signed() {
+            VM_OP_CHKS
+        } else {
+            VM_OP_CHKU
+        };
+        assemblize(
+            op,
+            &[
+                AssemblyValue::Register(register),
+                AssemblyValue::Value8(var_type.get_size() as u8 * 8),
+            ],
+        )
+    }
+
+    /**
+     * Convert a value in `register` between integer types, it is truncated to the size of
+     * the target type, then zero-extended if the target is unsigned or sign-extended if signed.
+     * Nothing is emitted if every value of `from` is kept.
+     */
+    fn compile_cast(from: &VariableType, target: &VariableType, register: u8) -> Vec<u8> {
+        let (from_size, target_size) = (from.get_size(), target.get_size());
+        let lossless = match (from.is_signed(), target.is_signed()) {
+            (true, false) => false,
+            (false, true) => target_size > from_size,
+            _ => target_size >= from_size,
         };
+        if lossless {
+            Vec::new()
+        } else {
+            Self::compile_wrap(target, register)
+        }
+    }
+
+    /**
+     * Wrap a value around to the size of its type, registers always keep
+     * unsigned values zero-extended and signed values sign-extended
+     *
+     * ```text
+     * and [register], [mask]
+     * ```
+     * or
+     * ```text
+     * shl [register], [64 - bits]
+     * sar [register], [64 - bits]
+     * ```
+     */
+    fn compile_wrap(var_type: &VariableType, register: u8) -> Vec<u8> {
+        let bits = var_type.get_size() as u64 * 8;
+        if matches!(var_type, VariableType::Bool | VariableType::Unkown) || bits >= 64 {
+            return Vec::new();
+        }
+        if !var_type.is_signed() {
+            return assemblize(
+                VM_OP_AND,
+                &[
+                    AssemblyValue::Register(register),
+                    AssemblyValue::Value64((1 << bits) - 1),
+                ],
+            );
+        }
+        let mut byte_code = Vec::new();
+        for op in [VM_OP_SHL, VM_OP_SAR] {
+            byte_code.extend(assemblize(
+                op,
+                &[
+                    AssemblyValue::Register(register),
+                    AssemblyValue::Value8(64 - bits as u8),
+                ],
+            ));
+        }
+        byte_code
+    }
+
+    /**
+     * Load the address of a variable into AR
+     *
+     * ```text
+     * mov ar, fp
+     * add/sub ar, [offset]
+     * ```
+     */
+    fn compile_var_address(offset: isize) -> Vec<u8> {
+        let mut byte_code = assemblize(
+            VM_OP_MOV,
+            &[
+                AssemblyValue::Register(VM_REG_AR),
+                AssemblyValue::Register(VM_REG_FP),
+            ],
+        );
+        /* params are above the frame pointer and locals are below it */
+        let op = if offset > 0 { VM_OP_ADD } else { VM_OP_SUB };
         byte_code.extend(assemblize(
             op,
             &[
-                AssemblyValue::Register(VM_REG_C0),
-                AssemblyValue::Register(VM_REG_C1),
+                AssemblyValue::Register(VM_REG_AR),
+                AssemblyValue::Value16(offset.unsigned_abs() as u16),
             ],
         ));
-        Ok(byte_code)
+        byte_code
     }
 
     /** compile for variable declaration */
     fn compile_new_var(
         &self,
-        ast: &AstNode,
+        var: &VarDecl,
         variables: &mut LocalVariables,
     ) -> Result<Vec<u8>, String> {
         let mut byte_code = Vec::new();
         let mut new_var = Variable::new();
-        new_var.name.clone_from(&ast.node(0).data.clone());
-        new_var.r#type = VariableType::from_string(&ast.node(1).data);
+        new_var.name.clone_from(&var.name.name);
+        new_var.r#type = match (&var.var_type, &var.init) {
+            (Some(var_type), _) => VariableType::from_string(&var_type.name),
+            /* inferred by the checker */
+            (None, Some(init)) => init.r#type.clone().unwrap_or(VariableType::Unkown),
+            (None, None) => VariableType::Unkown,
+        };
         {
             let size = new_var.r#type.get_size();
             new_var.size = size;
@@ -594,9 +1433,407 @@ impl Compiler {
                 ],
             ));
         }
-        let size = new_var.size as isize;
-        variables.modify_offset(size);
-        variables.push(new_var)?;
+        /* the variable is put below all variables in the frame */
+        new_var.offset = -((variables.stack_size() + new_var.size) as isize);
+        variables.push(new_var).map_err(|e| var.span.error(&e))?;
         Ok(byte_code)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::Checker;
+    use crate::token::generate_token;
+
+    /** compile and run a program, returns C0 after `main` returns */
+    fn run(code: &str) -> u64 {
+        execute(code, false).unwrap()
+    }
+
+    /** compile `code` and load it into a VM ready to run */
+    fn load(code: &str, checked: bool) -> Result<VM, String> {
+        let mut ast = Ast::from_tokens(generate_token(code).unwrap()).unwrap();
+        Checker::default().check(&mut ast).unwrap();
+        let mut symbols = Symbols::new();
+        let mut compiler = Compiler {
+            checked,
+            ..Default::default()
+        };
+        let mut byte_code = compiler.compile(&ast, &mut symbols, NORMAL_BASE_ADDR)?;
+        symbols.link(&mut byte_code)?;
+        let mut vm = VM::new();
+        vm.update_code(&byte_code);
+        vm.set_entry_point(symbols.lookup(ENTRY_SYMBOL).unwrap());
+        Ok(vm)
+    }
+
+    /** compile and run `code`, returns C0 or the trap stopping it */
+    fn execute(code: &str, checked: bool) -> Result<u64, Trap> {
+        let mut vm = load(code, checked).unwrap();
+        vm.run()?;
+        Ok(vm.c0)
+    }
+
+    /** run `return [expr];` in a `main` with `a` to `d` set to 7, 5, 3, 2 */
+    fn eval(expr: &str) -> u64 {
+        run(&format!(
+            "func sq(var x: u64) -> u64 {{ return x * x; }}
+            func main() -> u64 {{
+                var a: u64 = 7; var b: u64 = 5; var c: u64 = 3; var d: u64 = 2;
+                return {expr};
+            }}"
+        ))
+    }
+
+    #[test]
+    fn nested_operands() {
+        assert_eq!(eval("(a + b) * (c - d)"), 12);
+        assert_eq!(eval("a + b + c"), 15);
+        assert_eq!(eval("a - (b - (c - d))"), 3);
+        assert_eq!(eval("(a * b + c * d) * ((a + b) - (c - d))"), 451);
+    }
+
+    #[test]
+    fn spill_to_stack() {
+        assert_eq!(eval("1 + (2 + (3 + (4 + (5 + (6 + (7 + 8))))))"), 36);
+        assert_eq!(eval("a * (b + (c * (d + (a - (b - (c + d))))))"), 224);
+        assert_eq!(
+            eval("((a + b) * (c + d)) + ((a - b) * ((c + d) * ((a + d) - (b + c))))"),
+            70
+        );
+    }
+
+    #[test]
+    fn calls_in_expressions() {
+        assert_eq!(eval("sq(a) + sq(b)"), 74);
+        assert_eq!(eval("1 + (2 + (3 + (4 + sq(5 + (6 + sq(d))))))"), 235);
+        assert_eq!(eval("sq(sq(d) + a) - sq(b + (c - d))"), 85);
+    }
+
+    #[test]
+    fn recursion_with_locals() {
+        let code = "
+            func fib(var n: u64) -> u64 {
+                var a: u64 = n;
+                if a < 2 { return a; }
+                var b: u64 = fib(a - 1);
+                {
+                    var c: u64 = fib(a - 2);
+                    return b + c;
+                }
+            }
+            func main() -> u64 { var x: u64 = 1; return fib(15) + x; }";
+        assert_eq!(run(code), 611);
+    }
+
+    #[test]
+    fn sized_variables() {
+        let code = "func main() -> u8 {
+            var a: u8 = 1; var b: u8 = 2; var c: u16 = 3; var d: u8 = 4;
+            a = 255; b += 254; c = 65535;
+            return a - b - d;
+        }";
+        /* `b` is truncated to 0 without clobbering `a`, `c` and `d` */
+        assert_eq!(run(code), 251);
+        let code = "func main() -> i16 { var a: i16 = -300; var b: u8 = 7; return a; }";
+        assert_eq!(run(code), -300i64 as u64);
+        let code = "func main() -> i32 { var a: i32 = 0; a = -1; var b: u32 = 0; b = 4294967295; return a; }";
+        assert_eq!(run(code), u64::MAX);
+    }
+
+    #[test]
+    fn sized_params() {
+        let code = "
+            func check(var a: i8, var b: u16, var c: bool, var d: i32) -> bool {
+                return a == -5 && b == 513 && c && d == -7;
+            }
+            func main() -> bool { return check(-5, 513, true, -7); }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn signed_ops() {
+        let code = "
+            func main() -> bool {
+                var a: i64 = -7;
+                var b: i64 = 2;
+                var c: u64 = 7;
+                return a < 0 && a / b == -3 && a % b == -1 && a >> 1 == -4 && c >> 1 == 3;
+            }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn wrapping_arithmetic() {
+        let code = "
+            func main() -> bool {
+                var a: u8 = 250;
+                var b: i8 = 127;
+                var c: u64 = 0;
+                var d: u16 = 1;
+                return a + 10 == 4 && b + 1 == -128 && c - 1 == 18446744073709551615
+                    && ~a == 5 && d << 16 == 0;
+            }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let overflow = |body: &str| {
+            let code = format!("func main() -> u64 {{ var a: u8 = 250; var b: u64 = 0; {body} }}");
+            matches!(execute(&code, true), Err(Trap::Overflow { .. }))
+        };
+        assert!(overflow("a = a + 10; return 0;"));
+        assert!(overflow("return b - 1;"));
+        assert!(!overflow("a = a + 5; return b;"));
+        let code = "func main() -> u64 { var a: u64 = 0; return 1 / a; }";
+        assert!(matches!(
+            execute(code, false),
+            Err(Trap::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn short_circuit() {
+        let code = "
+            func fail() -> bool { var a: u64 = 0; return 1 / a == 0; }
+            func main() -> bool {
+                var a: u64 = 0;
+                var b: u64 = 6;
+                if a != 0 && b / a == 1 { return false; }
+                if a == 0 || fail() { a = 2; }
+                var c: bool = a == 2 && b == 6;
+                return c && (b == 0 || a == 2) || fail();
+            }";
+        /* `fail` would stop the VM if it ran */
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn inferred_variables() {
+        let code = "
+            func neg(var x: i16) -> i16 { return -x; }
+            func main() -> bool {
+                var a = 250u8;
+                var b = neg(300);
+                var c = a + 10;
+                var d = b < 0;
+                return c == 4 && b == -300 && d;
+            }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn forward_references() {
+        let code = "
+            func main() -> u64 { return collatz(27, 0); }
+            func collatz(var n: u64, var steps: u64) -> u64 {
+                if n == 1 { return steps; }
+                if is_even(n) { return collatz(n / 2, steps + 1); }
+                return collatz(3 * n + 1, steps + 1);
+            }
+            func is_even(var n: u64) -> bool {
+                if n == 0 { return true; }
+                return is_odd(n - 1);
+            }
+            func is_odd(var n: u64) -> bool {
+                if n == 0 { return false; }
+                return is_even(n - 1);
+            }";
+        assert_eq!(run(code), 111);
+    }
+
+    #[test]
+    fn casts() {
+        let code = "
+            func main() -> bool {
+                var a: u64 = 300;
+                var b: i8 = -1;
+                var c: u8 = 255;
+                return a as u8 == 44 && b as u16 == 65535 && c as i8 == -1 && b as i64 == -1
+                    && (b as u64) >> 63 == 1 && c as u32 + 1 == 256 && true as u8 == 1
+                    && -129 as i8 == 127;
+            }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn if_elif_else() {
+        let code = |n: u64| {
+            format!(
+                "func grade(var n: u64) -> u64 {{
+                    var r: u64 = 0;
+                    if n < 10 {{ r = 1; }} elif n < 20 {{ r = 2; }} elif n < 30 {{ r = 3; }} else {{ r = 4; }}
+                    return r;
+                }}
+                func main() -> u64 {{ return grade({n}); }}"
+            )
+        };
+        assert_eq!(run(&code(5)), 1);
+        assert_eq!(run(&code(15)), 2);
+        assert_eq!(run(&code(25)), 3);
+        assert_eq!(run(&code(35)), 4);
+        /* no branch is taken without `else` */
+        let code = "func main() -> u64 { var r: u64 = 7; if r == 0 { r = 1; } elif r == 1 { r = 2; } return r; }";
+        assert_eq!(run(code), 7);
+    }
+
+    #[test]
+    fn for_loops() {
+        let code = "func main() -> u64 {
+            var sum: u64 = 0;
+            for i in 0..10 { sum += i; }
+            for i: u8 in 1..10 step 3 { sum += i as u64 * 100; }
+            for (var i: u64 = 0; i < 5; i++) { sum += 1000; }
+            return sum;
+        }";
+        /* 45 + (1 + 4 + 7) * 100 + 5000 */
+        assert_eq!(run(code), 6245);
+        /* the loop variable is only visible in the loop, so it can be declared again */
+        let code = "func main() -> u64 {
+            var n: u64 = 0;
+            for i in 5..3 { n = 99; }
+            for (;;) { n++; if n == 3 { break; } }
+            var i: u64 = 10;
+            return i + n;
+        }";
+        assert_eq!(run(code), 13);
+    }
+
+    #[test]
+    fn for_loops_stop_before_overflow() {
+        /* the counter would pass the top of its type on the last step */
+        for checked in [false, true] {
+            let code = "func main() -> u64 {
+                var n: u64 = 0;
+                for i: u8 in 0..255 step 2 { n++; }
+                for i: u8 in 250..255 step 10 { n += 100; }
+                var sum: i64 = 0;
+                for i: i8 in -128..127 step 127 { sum += i as i64; }
+                for i: i8 in -100..100 step 50 { sum += i as i64; }
+                return n + (sum + 1000) as u64;
+            }";
+            /* 128 + 100 + (-128 - 1 + 126) + (-100 - 50 + 50) + 1000 */
+            assert_eq!(execute(code, checked), Ok(128 + 100 + 897));
+        }
+    }
+
+    #[test]
+    fn for_range_is_evaluated_once() {
+        let code = "
+            func count(var n: u64) -> u64 { return n; }
+            func main() -> u64 {
+                var n: u64 = 3;
+                var runs: u64 = 0;
+                for i in 0..n { n++; runs++; }
+                for i in 0..count(n) step count(2) { runs += 10; }
+                return runs;
+            }";
+        /* `n` is 6 after the first loop */
+        assert_eq!(run(code), 3 + 30);
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let code = "func main() -> u64 {
+            var sum: u64 = 0;
+            for i in 0..1000 {
+                var a: u64 = i;
+                if a % 2 == 0 { var b: u8 = 1; continue; }
+                {
+                    var c: u64 = a;
+                    if c > 900 { break; }
+                }
+                sum += a;
+            }
+            var n: u64 = 0;
+            while true {
+                var d: u16 = 1;
+                n += d as u64;
+                if n < 1000 { var e: u32 = 2; continue; }
+                break;
+            }
+            return sum + n;
+        }";
+        /* the odd numbers up to 899 */
+        let mut vm = load(code, false).unwrap();
+        let sp = vm.sp;
+        vm.run().unwrap();
+        assert_eq!(vm.c0, 202500 + 1000);
+        /* leaking stack space on each iteration would move `sp` */
+        assert_eq!(vm.sp, sp);
+        let error = |body: &str| load(&format!("func main() {{ {body} }}"), false).unwrap_err();
+        assert_eq!(error("break;"), "1:15: 'break' outside of a loop");
+        assert_eq!(error("{ continue; }"), "1:17: 'continue' outside of a loop");
+    }
+
+    #[test]
+    fn labeled_loops() {
+        let code = "func main() -> u64 {
+            var pairs: u64 = 0;
+            'outer: for i in 0..10 {
+                var a: u64 = i;
+                'inner: while true {
+                    for j in 0..10 {
+                        var b: u64 = j;
+                        if b > a { continue 'outer; }
+                        if a == 8 { break 'outer; }
+                        pairs++;
+                    }
+                    break 'inner;
+                }
+            }
+            return pairs;
+        }";
+        /* 1 + 2 + ... + 8 pairs with j <= i before `i` reaches 8 */
+        let mut vm = load(code, false).unwrap();
+        let sp = vm.sp;
+        vm.run().unwrap();
+        assert_eq!(vm.c0, 36);
+        assert_eq!(vm.sp, sp);
+        let code = "func main() { 'a: while true { break 'b; } }";
+        assert_eq!(
+            load(code, false).unwrap_err(),
+            "1:38: use of undeclared label 'b"
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let code = "func main() -> bool {
+            var a: u64 = 10;
+            a += 5; a -= 3; a *= 4; a /= 5; a %= 7; a <<= 4; a >>= 2; a &= 13; a |= 32; a ^= 3;
+            a++; a++; a--;
+            var b: i64 = -7;
+            var c: i64 = -7;
+            var d: i64 = -64;
+            var e: i8 = 127;
+            b /= 2; c %= 2; d >>= 3; e++;
+            return a == 44 && b == -3 && c == -1 && d == -8 && e == -128;
+        }";
+        assert_eq!(run(code), 1);
+    }
+
+    #[test]
+    fn return_from_nested_blocks() {
+        let code = "
+            func find(var n: u64) -> u64 {
+                var a: u64 = 1;
+                for i in 0..100 {
+                    var b: u32 = 2;
+                    {
+                        var c: u16 = 3;
+                        if i == n { var d: u8 = 4; return i + a + b as u64 + c as u64 + d as u64; }
+                    }
+                }
+                return 0;
+            }
+            func main() -> u64 { var x: u64 = 100; return find(7) + find(50) + x; }";
+        let mut vm = load(code, false).unwrap();
+        let sp = vm.sp;
+        vm.run().unwrap();
+        assert_eq!(vm.c0, 17 + 60 + 100);
+        assert_eq!(vm.sp, sp);
+    }
+}
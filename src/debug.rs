@@ -1,59 +1,11 @@
-use crate::ast::AstNode;
-use crate::token::Token;
+use crate::ast::*;
+use crate::token::{Span, Token};
 use crate::vm::VM;
 use std::fmt::Debug;
 
-const AST_TYPES: [&str; 38] = [
-    "AST_TYPE_UNDEFINED",
-    "AST_TYPE_PROGRAM",
-    "AST_TYPE_IDENTIFIER",
-    "AST_TYPE_VAR_DECLARE",
-    "AST_TYPE_VAR_TYPE",
-    "AST_TYPE_VAR_SET_VALUE",
-    "AST_TYPE_FUNC_DEF",
-    "AST_TYPE_FUNC_CALL",
-    "AST_TYPE_CODE_BLOCK",
-    "AST_TYPE_PARAMS",
-    "AST_TYPE_IF",
-    "AST_TYPE_ELIF",
-    "AST_TYPE_ELSE",
-    "AST_TYPE_FOR",
-    "AST_TYPE_WHILE",
-    "AST_TYPE_ADD",
-    "AST_TYPE_SUB",
-    "AST_TYPE_MUL",
-    "AST_TYPE_DIV",
-    "AST_TYPE_MOD",
-    "AST_TYPE_GT",
-    "AST_TYPE_LT",
-    "AST_TYPE_GE",
-    "AST_TYPE_LE",
-    "AST_TYPE_EQU",
-    "AST_TYPE_NEQU",
-    "AST_TYPE_SHL",
-    "AST_TYPE_SHR",
-    "AST_TYPE_AND",
-    "AST_TYPE_OR",
-    "AST_TYPE_LOGIC_AND",
-    "AST_TYPE_LOGIC_OR",
-    "AST_TYPE_VALUE",
-    "AST_TYPE_BREAK",
-    "AST_TYPE_CONTINUE",
-    "AST_TYPE_RETURN",
-    "AST_TYPE_INDEX",
-    "AST_TYPE_CHILD",
-];
-
-impl Debug for AstNode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", AST_TYPES[self.r#type as usize], self.data)?;
-        Ok(())
-    }
-}
-
 impl Debug for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\t{:?}", self.name, self.r#type)?;
+        write!(f, "{}\t{:?}\t{}", self.name, self.r#type, self.span)?;
         Ok(())
     }
 }
@@ -66,26 +18,212 @@ impl Debug for VM {
         writeln!(f, "C3: 0x{:08X}", self.c3)?;
         writeln!(f, "SP: 0x{:08X}", self.sp)?;
         writeln!(f, "IP: 0x{:08X}", self.ip)?;
-        write!(f, "AR: 0x{:08X}", self.ar)?;
+        writeln!(f, "AR: 0x{:08X}", self.ar)?;
+        write!(f, "FP: 0x{:08X}", self.fp)?;
         Ok(())
     }
 }
 
-fn _print_ast(node: &AstNode, re: usize) {
-    fn print_node(node: &AstNode, tab: usize) {
-        for _ in 0..tab {
-            print!("\t");
+fn print_line(tab: usize, text: &str, span: Span) {
+    for _ in 0..tab {
+        print!("\t");
+    }
+    println!("{text} ({span})");
+}
+
+fn label_suffix(label: &Option<Ident>) -> String {
+    match label {
+        Some(label) => format!(" {}", label.name),
+        None => String::new(),
+    }
+}
+
+fn _print_block(block: &Block, tab: usize) {
+    print_line(tab, "CODE_BLOCK", block.span);
+    for stmt in &block.stmts {
+        _print_stmt(stmt, tab + 1);
+    }
+}
+
+fn _print_if(if_stmt: &IfStmt, name: &str, tab: usize) {
+    print_line(tab, name, if_stmt.span);
+    _print_expr(&if_stmt.cond, tab + 1);
+    _print_block(&if_stmt.body, tab + 1);
+    match &if_stmt.else_branch {
+        Some(ElseBranch::Elif(elif)) => _print_if(elif, "ELIF", tab),
+        Some(ElseBranch::Else(block)) => {
+            print_line(tab, "ELSE", block.span);
+            _print_block(block, tab + 1);
+        }
+        None => {}
+    }
+}
+
+fn _print_for(for_stmt: &ForStmt, tab: usize) {
+    match &for_stmt.header {
+        ForHeader::Range {
+            var,
+            var_type,
+            start,
+            end,
+            step,
+        } => {
+            let var_type = match var_type {
+                Some(var_type) => format!(": {}", var_type.name),
+                None => String::new(),
+            };
+            print_line(
+                tab,
+                &format!(
+                    "FOR{} {}{var_type} IN",
+                    label_suffix(&for_stmt.label),
+                    var.name
+                ),
+                for_stmt.span,
+            );
+            _print_expr(start, tab + 1);
+            _print_expr(end, tab + 1);
+            if let Some(step) = step {
+                print_line(tab + 1, "STEP", step.span);
+                _print_expr(step, tab + 2);
+            }
+        }
+        ForHeader::CStyle { init, cond, step } => {
+            print_line(
+                tab,
+                &format!("FOR{}", label_suffix(&for_stmt.label)),
+                for_stmt.span,
+            );
+            if let Some(init) = init {
+                _print_stmt(init, tab + 1);
+            }
+            if let Some(cond) = cond {
+                _print_expr(cond, tab + 1);
+            }
+            if let Some(step) = step {
+                _print_stmt(step, tab + 1);
+            }
+        }
+    }
+    _print_block(&for_stmt.body, tab + 1);
+}
+
+fn _print_var(var: &VarDecl, tab: usize) {
+    let var_type = match &var.var_type {
+        Some(var_type) => format!(": {}", var_type.name),
+        None => String::new(),
+    };
+    print_line(
+        tab,
+        &format!("VAR_DECLARE {}{var_type}", var.name.name),
+        var.span,
+    );
+    if let Some(init) = &var.init {
+        _print_expr(init, tab + 1);
+    }
+}
+
+fn _print_stmt(stmt: &Stmt, tab: usize) {
+    match &stmt.kind {
+        StmtKind::Var(var) => _print_var(var, tab),
+        StmtKind::Assign { target, op, value } => {
+            let op = op.map(|op| op.to_string()).unwrap_or_default();
+            print_line(
+                tab,
+                &format!("VAR_SET_VALUE {} {op}=", target.name),
+                stmt.span,
+            );
+            _print_expr(value, tab + 1);
+        }
+        StmtKind::Expr(expr) => _print_expr(expr, tab),
+        StmtKind::If(if_stmt) => _print_if(if_stmt, "IF", tab),
+        StmtKind::While { label, cond, body } => {
+            print_line(tab, &format!("WHILE{}", label_suffix(label)), stmt.span);
+            _print_expr(cond, tab + 1);
+            _print_block(body, tab + 1);
         }
-        println!("{node:?}");
+        StmtKind::For(for_stmt) => _print_for(for_stmt, tab),
+        StmtKind::Return(value) => {
+            print_line(tab, "RETURN", stmt.span);
+            if let Some(value) = value {
+                _print_expr(value, tab + 1);
+            }
+        }
+        StmtKind::Break(label) => {
+            print_line(tab, &format!("BREAK{}", label_suffix(label)), stmt.span);
+        }
+        StmtKind::Continue(label) => {
+            print_line(tab, &format!("CONTINUE{}", label_suffix(label)), stmt.span);
+        }
+        StmtKind::Block(block) => _print_block(block, tab),
     }
-    print_node(node, re);
-    for i in &node.nodes {
-        _print_ast(&(i.borrow()), re + 1);
+}
+
+fn _print_expr(expr: &Expr, tab: usize) {
+    match &expr.kind {
+        ExprKind::Literal(literal) => {
+            print_line(tab, &format!("VALUE {literal:?}"), expr.span);
+        }
+        ExprKind::Identifier(name) => {
+            print_line(tab, &format!("IDENTIFIER {name}"), expr.span);
+        }
+        ExprKind::Binary { op, left, right } => {
+            print_line(tab, &format!("BINARY {op}"), expr.span);
+            _print_expr(left, tab + 1);
+            _print_expr(right, tab + 1);
+        }
+        ExprKind::Unary { op, operand } => {
+            print_line(tab, &format!("UNARY {op}"), expr.span);
+            _print_expr(operand, tab + 1);
+        }
+        ExprKind::Call { name, args } => {
+            print_line(tab, &format!("FUNC_CALL {}", name.name), expr.span);
+            for arg in args {
+                _print_expr(arg, tab + 1);
+            }
+        }
+        ExprKind::Index { base, index } => {
+            print_line(tab, "INDEX", expr.span);
+            _print_expr(base, tab + 1);
+            _print_expr(index, tab + 1);
+        }
+        ExprKind::Member { base, member } => {
+            print_line(tab, &format!("CHILD {}", member.name), expr.span);
+            _print_expr(base, tab + 1);
+        }
+        ExprKind::Cast { operand, target } => {
+            print_line(tab, &format!("CAST {}", target.name), expr.span);
+            _print_expr(operand, tab + 1);
+        }
     }
 }
+
 /** print AST */
-pub fn print_ast(node: &AstNode) {
-    _print_ast(node, 0);
+pub fn print_ast(ast: &Ast) {
+    for item in &ast.items {
+        match item {
+            Item::Func(func) => {
+                let ret_type = match &func.ret_type {
+                    Some(ret_type) => format!(" -> {}", ret_type.name),
+                    None => String::new(),
+                };
+                print_line(
+                    0,
+                    &format!("FUNC_DEF {}{ret_type}", func.name.name),
+                    func.span,
+                );
+                for param in &func.params {
+                    print_line(
+                        1,
+                        &format!("PARAM {}: {}", param.name.name, param.var_type.name),
+                        param.span,
+                    );
+                }
+                _print_block(&func.body, 1);
+            }
+            Item::Global(var) => _print_var(var, 0),
+        }
+    }
 }
 
 /** print tokens */
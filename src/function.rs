@@ -4,6 +4,8 @@ use crate::variable::VariableType;
 pub struct Function {
     pub name: String,
     pub params: Vec<VariableType>,
+    /** `None` if the function doesn't return a value */
+    pub ret_type: Option<VariableType>,
 }
 
 #[derive(Default, Debug)]
@@ -15,10 +17,11 @@ impl Functions {
     pub fn lookup(&self, name: &str) -> Option<&Function> {
         self.functions.iter().find(|&i| i.name == name)
     }
-    pub fn add(&mut self, name: &str, params: &[VariableType]) {
+    pub fn add(&mut self, name: &str, params: &[VariableType], ret_type: Option<VariableType>) {
         self.functions.push(Function {
             name: name.to_string(),
             params: params.to_vec(),
+            ret_type,
         })
     }
 }
@@ -1,8 +1,10 @@
 pub mod assembly;
 pub mod ast;
+pub mod check;
 pub mod compile;
 pub mod debug;
 mod function;
+pub mod parser;
 pub mod symbol;
 pub mod token;
 pub mod variable;
@@ -1,46 +1,63 @@
-mod assembly;
-mod ast;
-mod compile;
-mod debug;
-mod function;
-mod symbol;
-mod token;
-mod variable;
-mod vm;
-mod vram;
-
-use ast::AstNode;
+use anicat::ast::Ast;
+use anicat::vm::VM;
+use anicat::*;
 use std::io::*;
-use vm::VM;
 
 fn main() -> std::io::Result<()> {
     //print!("\x1b[H\x1b[2J\x1b[3J");
-    let code = std::fs::read_to_string("test.ac")?;
+    let path = "test.ac";
+    let code = std::fs::read_to_string(path)?;
     /* generate tokens */
-    let tokens = token::generate_token(&code).unwrap();
+    let tokens = match token::generate_token(&code) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            eprintln!("{path}:{e}");
+            return Err(Error::other(""));
+        }
+    };
     /* generate AST */
-    let ast = AstNode::from_tokens(&mut tokens.into_iter());
+    let mut ast = match Ast::from_tokens(tokens) {
+        Ok(ast) => ast,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{path}:{e}");
+            }
+            return Err(Error::other(""));
+        }
+    };
     //debug::print_ast(&ast);
+    /* check types */
+    if let Err(errors) = check::Checker::default().check(&mut ast) {
+        for e in errors {
+            eprintln!("{path}:{e}");
+        }
+        return Err(Error::other(""));
+    }
 
     let mut symbols = symbol::Symbols::new();
-    let compiler = compile::Compiler::default();
-    let result = compiler.compile(&ast, None, &mut symbols, compile::NORMAL_BASE_ADDR);
+    let mut compiler = compile::Compiler::default();
+    compiler.checked = std::env::args().any(|arg| arg == "--checked");
+    let result = compiler.compile(&ast, &mut symbols, compile::NORMAL_BASE_ADDR);
     let mut byte_code = match result {
         Ok(byte_code) => byte_code,
         Err(e) => {
-            eprintln!("{e}");
-            return Err(Error::new(ErrorKind::Other, ""));
+            eprintln!("{path}:{e}");
+            return Err(Error::other(""));
         }
     };
-    byte_code.extend(assembly::assemblize(vm::VM_OP_HAL, &[]));
-    symbols.link(&mut byte_code);
+    if let Err(e) = symbols.link(&mut byte_code) {
+        eprintln!("{path}: {e}");
+        return Err(Error::other(""));
+    }
 
     std::fs::write("byte_code", &byte_code)?;
 
     let mut vm = VM::new();
     vm.update_code(&byte_code);
-    vm.set_entry_point(symbols.lookup("main").unwrap());
-    vm.run();
+    vm.set_entry_point(symbols.lookup(compile::ENTRY_SYMBOL).unwrap());
+    if let Err(trap) = vm.run() {
+        eprintln!("{path}: {trap}");
+    }
     println!("{vm:?}");
     Ok(())
 }
@@ -0,0 +1,903 @@
+use crate::ast::*;
+use crate::token::*;
+
+/** get the binary operator of a token */
+fn binary_op(r#type: &TokenType) -> Option<BinaryOp> {
+    let op = match r#type {
+        TokenType::Add => BinaryOp::Add,
+        TokenType::Sub => BinaryOp::Sub,
+        TokenType::Mul => BinaryOp::Mul,
+        TokenType::Div => BinaryOp::Div,
+        TokenType::Mod => BinaryOp::Mod,
+        TokenType::Shl => BinaryOp::Shl,
+        TokenType::Shr => BinaryOp::Shr,
+        TokenType::And => BinaryOp::And,
+        TokenType::Or => BinaryOp::Or,
+        TokenType::Xor => BinaryOp::Xor,
+        TokenType::LogicAnd => BinaryOp::LogicAnd,
+        TokenType::LogicOr => BinaryOp::LogicOr,
+        TokenType::IsEqu => BinaryOp::Equ,
+        TokenType::NotEqu => BinaryOp::NotEqu,
+        TokenType::GT => BinaryOp::GT,
+        TokenType::LT => BinaryOp::LT,
+        TokenType::Ge => BinaryOp::Ge,
+        TokenType::Le => BinaryOp::Le,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/** get the operator of an assignment token, `Some(None)` for a plain `=` */
+fn assign_op(r#type: &TokenType) -> Option<Option<BinaryOp>> {
+    let op = match r#type {
+        TokenType::Equ => None,
+        TokenType::AddEqu => Some(BinaryOp::Add),
+        TokenType::SubEqu => Some(BinaryOp::Sub),
+        TokenType::MulEqu => Some(BinaryOp::Mul),
+        TokenType::DivEqu => Some(BinaryOp::Div),
+        TokenType::ModEqu => Some(BinaryOp::Mod),
+        TokenType::ShlEqu => Some(BinaryOp::Shl),
+        TokenType::ShrEqu => Some(BinaryOp::Shr),
+        TokenType::AndEqu => Some(BinaryOp::And),
+        TokenType::OrEqu => Some(BinaryOp::Or),
+        TokenType::XorEqu => Some(BinaryOp::Xor),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/** detect if a statement can start with the token, used to resynchronize after an error */
+fn starts_stmt(token: &Token) -> bool {
+    token.r#type == TokenType::Keyword
+        && matches!(
+            token.name.as_str(),
+            "var" | "if" | "while" | "for" | "return" | "break" | "continue" | "func"
+        )
+}
+
+/** Kinds of errors found while parsing tokens */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /** `found` is the name of the unexpected token, `None` at the end of file */
+    Unexpected {
+        expected: String,
+        found: Option<String>,
+    },
+    /** a malformed or out of range literal */
+    InvalidLiteral(String),
+}
+
+/** An error found while parsing tokens */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.span)?;
+        match &self.kind {
+            ParseErrorKind::Unexpected {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {expected}, found '{found}'"),
+            ParseErrorKind::Unexpected {
+                expected,
+                found: None,
+            } => write!(f, "expected {expected}, found end of file"),
+            ParseErrorKind::InvalidLiteral(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+/** A recursive descent parser generating AST from tokens */
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /** span of the last consumed token */
+    last_span: Span,
+    /** errors recovered from so far */
+    errors: Vec<ParseError>,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            last_span: Span::default(),
+            errors: Vec::new(),
+        }
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn peek_second(&self) -> Option<&Token> {
+        self.tokens.get(self.pos + 1)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned()?;
+        self.pos += 1;
+        self.last_span = token.span;
+        Some(token)
+    }
+    /** detect if the next token has the type */
+    fn check(&self, r#type: TokenType) -> bool {
+        self.peek().is_some_and(|token| token.r#type == r#type)
+    }
+    /** detect if the next token is the keyword */
+    fn check_keyword(&self, keyword: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.r#type == TokenType::Keyword && token.name == keyword)
+    }
+    /** detect if the next token is the separator, `Split` covers both ',' and ';' */
+    fn check_split(&self, split: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.r#type == TokenType::Split && token.name == split)
+    }
+    /** consume the next token if it has the type */
+    fn eat(&mut self, r#type: TokenType) -> bool {
+        if self.check(r#type) {
+            self.next();
+            return true;
+        }
+        false
+    }
+    /** consume the next token if it is the keyword */
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.check_keyword(keyword) {
+            self.next();
+            return true;
+        }
+        false
+    }
+    /** the position to report when the next token is unexpected */
+    fn error_span(&self) -> Span {
+        match self.peek() {
+            Some(token) => token.span,
+            None => Span::new(self.last_span.end, self.last_span.end),
+        }
+    }
+    /** make an "expected X, found Y" error at the next token */
+    fn unexpected(&self, expected: &str) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::Unexpected {
+                expected: expected.to_string(),
+                found: self.peek().map(|token| token.name.clone()),
+            },
+            span: self.error_span(),
+        }
+    }
+    /** record an error, an error at the same place as the previous one is dropped */
+    fn report(&mut self, error: ParseError) {
+        if self.errors.last().map(|last| last.span) != Some(error.span) {
+            self.errors.push(error);
+        }
+    }
+    /**
+     * Skip tokens after an error until a statement boundary
+     *
+     * Stops after a `;`, or in front of a `}` or a keyword starting a statement,
+     * nested `{...}` are skipped as a whole. At least one token is skipped if `start`
+     * is still the position of the next token so that the caller always makes progress.
+     */
+    fn synchronize(&mut self, start: usize) {
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token.r#type {
+                TokenType::Split if depth == 0 && token.name == ";" => {
+                    self.next();
+                    return;
+                }
+                TokenType::LlBkt => depth += 1,
+                TokenType::RlBkt if depth == 0 => break,
+                TokenType::RlBkt => depth -= 1,
+                _ if depth == 0 && starts_stmt(token) => break,
+                _ => {}
+            }
+            self.next();
+        }
+        if self.pos == start {
+            self.next();
+        }
+    }
+    /** consume a token with the type, or report what is expected */
+    fn expect(&mut self, r#type: TokenType, expected: &str) -> Result<Token, ParseError> {
+        if self.check(r#type) {
+            return Ok(self.next().unwrap());
+        }
+        Err(self.unexpected(expected))
+    }
+    fn expect_split(&mut self, split: &str) -> Result<(), ParseError> {
+        if self.check_split(split) {
+            self.next();
+            return Ok(());
+        }
+        Err(self.unexpected(&format!("'{split}'")))
+    }
+    fn expect_ident(&mut self, expected: &str) -> Result<Ident, ParseError> {
+        let token = self.expect(TokenType::Name, expected)?;
+        Ok(Ident {
+            name: token.name,
+            span: token.span,
+        })
+    }
+    /** the span from `start` to the last consumed token */
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(self.last_span)
+    }
+    /** skip `;` and `,` */
+    fn skip_splits(&mut self) {
+        while self.eat(TokenType::Split) {}
+    }
+
+    /** parse a whole program, reporting every syntax error recovered from */
+    pub fn parse(&mut self) -> Result<Ast, Vec<ParseError>> {
+        let mut ast = Ast::default();
+        loop {
+            self.skip_splits();
+            if self.peek().is_none() {
+                break;
+            }
+            let start = self.pos;
+            let item = if self.check_keyword("func") {
+                self.parse_func_def().map(Item::Func)
+            } else if self.check_keyword("var") {
+                self.parse_var_decl().map(Item::Global)
+            } else {
+                Err(self.unexpected("'func' or 'var'"))
+            };
+            match item {
+                Ok(item) => ast.items.push(item),
+                Err(e) => {
+                    self.report(e);
+                    /* skip to the next definition */
+                    let mut start = start;
+                    loop {
+                        self.synchronize(start);
+                        if self.peek().is_none()
+                            || self.check_keyword("func")
+                            || self.check_keyword("var")
+                        {
+                            break;
+                        }
+                        start = self.pos;
+                    }
+                }
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(ast)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+    /** `func name(var a: type, ...) -> type {...}` */
+    fn parse_func_def(&mut self) -> Result<FuncDef, ParseError> {
+        let start = self.next().unwrap().span;
+        let name = self.expect_ident("function name")?;
+        self.expect(TokenType::LsBkt, "'('")?;
+        let mut params = Vec::new();
+        while !self.eat(TokenType::RsBkt) {
+            let param_start = self.error_span();
+            self.eat_keyword("var");
+            let param_name = self.expect_ident("parameter name")?;
+            self.expect(TokenType::Explain, "':'")?;
+            let var_type = self.expect_ident("parameter type")?;
+            params.push(Param {
+                name: param_name,
+                var_type,
+                span: self.span_from(param_start),
+            });
+            if !self.check(TokenType::RsBkt) {
+                self.expect(TokenType::Split, "',' or ')'")?;
+            }
+        }
+        let mut ret_type = None;
+        if self.check(TokenType::Explain) && self.peek().unwrap().name == "->" {
+            self.next();
+            ret_type = Some(self.expect_ident("return type")?);
+        }
+        let body = self.parse_block()?;
+        Ok(FuncDef {
+            name,
+            params,
+            ret_type,
+            body,
+            span: self.span_from(start),
+        })
+    }
+    /** `{ statements }` */
+    fn parse_block(&mut self) -> Result<Block, ParseError> {
+        let start = self.expect(TokenType::LlBkt, "'{'")?.span;
+        let mut stmts = Vec::new();
+        loop {
+            self.skip_splits();
+            if self.eat(TokenType::RlBkt) {
+                break;
+            }
+            if self.peek().is_none() {
+                return Err(self.unexpected("'}'"));
+            }
+            let stmt_start = self.pos;
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(e) => {
+                    self.report(e);
+                    self.synchronize(stmt_start);
+                }
+            }
+        }
+        Ok(Block {
+            stmts,
+            span: self.span_from(start),
+        })
+    }
+    fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.error_span();
+        let kind = if self.check_keyword("var") {
+            StmtKind::Var(self.parse_var_decl()?)
+        } else if self.check_keyword("if") {
+            StmtKind::If(self.parse_if()?)
+        }
+        /* `'label: while` and `'label: for` */
+        else if self.check(TokenType::Label) {
+            let label = self.expect_label()?;
+            if !self.check(TokenType::Explain) || self.peek().unwrap().name != ":" {
+                return Err(self.unexpected("':'"));
+            }
+            self.next();
+            self.parse_loop(Some(label))?
+        } else if self.check_keyword("for") || self.check_keyword("while") {
+            self.parse_loop(None)?
+        } else if self.eat_keyword("return") {
+            if self.peek().is_none() || self.check(TokenType::Split) || self.check(TokenType::RlBkt)
+            {
+                StmtKind::Return(None)
+            } else {
+                StmtKind::Return(Some(self.parse_expr()?))
+            }
+        } else if self.eat_keyword("break") {
+            StmtKind::Break(self.parse_label_ref()?)
+        } else if self.eat_keyword("continue") {
+            StmtKind::Continue(self.parse_label_ref()?)
+        } else if self.check(TokenType::LlBkt) {
+            StmtKind::Block(self.parse_block()?)
+        } else if self.check(TokenType::Name)
+            && self
+                .peek_second()
+                .is_some_and(|token| assign_op(&token.r#type).is_some())
+        {
+            let target = self.expect_ident("variable name")?;
+            let op = assign_op(&self.next().unwrap().r#type).unwrap();
+            let value = self.parse_expr()?;
+            StmtKind::Assign { target, op, value }
+        }
+        /* i++, i-- */
+        else if self.check(TokenType::Name)
+            && self.peek_second().is_some_and(|token| {
+                token.r#type == TokenType::Inc || token.r#type == TokenType::Dec
+            })
+        {
+            let target = self.expect_ident("variable name")?;
+            let token = self.next().unwrap();
+            let op = match token.r#type {
+                TokenType::Inc => BinaryOp::Add,
+                _ => BinaryOp::Sub,
+            };
+            let value = Expr {
+                kind: ExprKind::Literal(Literal::Integer {
+                    value: 1,
+                    suffix: None,
+                }),
+                span: token.span,
+                r#type: None,
+            };
+            StmtKind::Assign {
+                target,
+                op: Some(op),
+                value,
+            }
+        } else {
+            StmtKind::Expr(self.parse_expr()?)
+        };
+        Ok(Stmt {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+    /** `var name: type` or `var name: type = value` */
+    fn parse_var_decl(&mut self) -> Result<VarDecl, ParseError> {
+        let start = self.next().unwrap().span;
+        let name = self.expect_ident("variable name")?;
+        let mut var_type = None;
+        let mut init = None;
+        if self.eat(TokenType::Explain) {
+            var_type = Some(self.expect_ident("variable type")?);
+            if self.eat(TokenType::Equ) {
+                init = Some(self.parse_expr()?);
+            }
+        }
+        /* the type is inferred from the initial value */
+        else {
+            self.expect(TokenType::Equ, "':' or '='")?;
+            init = Some(self.parse_expr()?);
+        }
+        Ok(VarDecl {
+            name,
+            var_type,
+            init,
+            span: self.span_from(start),
+        })
+    }
+    /** `if cond {...}`, `if` and `elif` can be followed by `elif` or `else` */
+    fn parse_if(&mut self) -> Result<IfStmt, ParseError> {
+        let start = self.next().unwrap().span;
+        let cond = self.parse_expr()?;
+        let body = self.parse_block()?;
+        let else_branch = if self.check_keyword("elif") {
+            Some(ElseBranch::Elif(Box::new(self.parse_if()?)))
+        } else if self.eat_keyword("else") {
+            Some(ElseBranch::Else(self.parse_block()?))
+        } else {
+            None
+        };
+        Ok(IfStmt {
+            cond,
+            body,
+            else_branch,
+            span: self.span_from(start),
+        })
+    }
+
+    fn expect_label(&mut self) -> Result<Ident, ParseError> {
+        let token = self.expect(TokenType::Label, "label")?;
+        Ok(Ident {
+            name: token.name,
+            span: token.span,
+        })
+    }
+    /** the optional label after `break` and `continue` */
+    fn parse_label_ref(&mut self) -> Result<Option<Ident>, ParseError> {
+        if self.check(TokenType::Label) {
+            return Ok(Some(self.expect_label()?));
+        }
+        Ok(None)
+    }
+    /** `while cond {...}` or a `for` loop, `label` is written in front of the loop */
+    fn parse_loop(&mut self, label: Option<Ident>) -> Result<StmtKind, ParseError> {
+        if self.check_keyword("for") {
+            return Ok(StmtKind::For(self.parse_for(label)?));
+        }
+        if !self.eat_keyword("while") {
+            return Err(self.unexpected("'while' or 'for'"));
+        }
+        let cond = self.parse_expr()?;
+        let body = self.parse_block()?;
+        Ok(StmtKind::While { label, cond, body })
+    }
+    /** `for i in start..end step n {...}` or `for (init; cond; step) {...}` */
+    fn parse_for(&mut self, label: Option<Ident>) -> Result<ForStmt, ParseError> {
+        let start = self.next().unwrap().span;
+        let header = if self.eat(TokenType::LsBkt) {
+            let mut init = None;
+            if !self.check_split(";") {
+                init = Some(Box::new(self.parse_for_clause(true)?));
+            }
+            self.expect_split(";")?;
+            let mut cond = None;
+            if !self.check_split(";") {
+                cond = Some(self.parse_expr()?);
+            }
+            self.expect_split(";")?;
+            let mut step = None;
+            if !self.check(TokenType::RsBkt) {
+                step = Some(Box::new(self.parse_for_clause(false)?));
+            }
+            self.expect(TokenType::RsBkt, "')'")?;
+            ForHeader::CStyle { init, cond, step }
+        } else {
+            let var = self.expect_ident("loop variable or '('")?;
+            let mut var_type = None;
+            if self.check(TokenType::Explain) && self.peek().unwrap().name == ":" {
+                self.next();
+                var_type = Some(self.expect_ident("variable type")?);
+            }
+            if !self.eat_keyword("in") {
+                return Err(self.unexpected("'in'"));
+            }
+            let range_start = self.parse_expr()?;
+            self.expect(TokenType::Range, "'..'")?;
+            let end = self.parse_expr()?;
+            /* `step` is only special after a range */
+            let mut step = None;
+            if self.check(TokenType::Name) && self.peek().unwrap().name == "step" {
+                self.next();
+                step = Some(Box::new(self.parse_expr()?));
+            }
+            ForHeader::Range {
+                var,
+                var_type,
+                start: Box::new(range_start),
+                end: Box::new(end),
+                step,
+            }
+        };
+        let body = self.parse_block()?;
+        Ok(ForStmt {
+            label,
+            header,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    /**
+     * Parse the init or the step of a C-style `for`
+     *
+     * They only take assignments and expressions, and `var` is only allowed in the init,
+     * because the step runs outside of the loop body on every iteration.
+     */
+    fn parse_for_clause(&mut self, is_init: bool) -> Result<Stmt, ParseError> {
+        let is_simple = !self.check(TokenType::LlBkt)
+            && !self.check(TokenType::Label)
+            && !["if", "for", "while", "return", "break", "continue"]
+                .iter()
+                .any(|keyword| self.check_keyword(keyword));
+        if is_init && !is_simple {
+            return Err(self.unexpected("variable declaration, assignment or expression"));
+        }
+        if !is_init && (!is_simple || self.check_keyword("var")) {
+            return Err(self.unexpected("assignment or expression"));
+        }
+        self.parse_stmt()
+    }
+
+    /** parse an expression */
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(1)
+    }
+    /**
+     * Parse binary operators by precedence climbing
+     *
+     * Only operators with a priority of at least `min_priority` are merged here,
+     * the right operand only takes tighter operators so that `a - b - c` is `(a - b) - c`.
+     */
+    fn parse_binary(&mut self, min_priority: u8) -> Result<Expr, ParseError> {
+        let mut left = self.parse_cast()?;
+        while let Some(op) = self.peek().and_then(|token| binary_op(&token.r#type)) {
+            if op.priority() < min_priority {
+                break;
+            }
+            self.next();
+            let right = self.parse_binary(op.priority() + 1)?;
+            left = Expr {
+                span: left.span.merge(right.span),
+                kind: ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                r#type: None,
+            };
+        }
+        Ok(left)
+    }
+    /** parse `operand as type`, which binds tighter than binary operators */
+    fn parse_cast(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        while self.eat_keyword("as") {
+            let target = self.expect_ident("type")?;
+            expr = Expr {
+                span: expr.span.merge(target.span),
+                kind: ExprKind::Cast {
+                    operand: Box::new(expr),
+                    target,
+                },
+                r#type: None,
+            };
+        }
+        Ok(expr)
+    }
+    /** parse prefix `-`, `!` and `~` */
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.error_span();
+        let op = match self.peek().map(|token| &token.r#type) {
+            /* `-` in front of a number is left to `parse_operand` */
+            Some(TokenType::Sub)
+                if self
+                    .peek_second()
+                    .is_some_and(|token| token.r#type == TokenType::Number) =>
+            {
+                return self.parse_operand()
+            }
+            Some(TokenType::Sub) => UnaryOp::Neg,
+            Some(TokenType::Not) => UnaryOp::Not,
+            Some(TokenType::BitNot) => UnaryOp::BitNot,
+            _ => return self.parse_operand(),
+        };
+        self.next();
+        let operand = self.parse_unary()?;
+        Ok(Expr {
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span: self.span_from(start),
+            r#type: None,
+        })
+    }
+    /** parse a literal, a variable, a function call or a `(...)` group with postfixes */
+    fn parse_operand(&mut self) -> Result<Expr, ParseError> {
+        let start = self.error_span();
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(self.unexpected("expression")),
+        };
+        let kind = match token.r#type {
+            TokenType::Number => self.parse_number(&token.name, token.span)?,
+            /* a `-` in front of a number is folded into a negative literal */
+            TokenType::Sub if self.check(TokenType::Number) => {
+                let number = self.next().unwrap();
+                self.parse_number(&format!("-{}", number.name), self.span_from(start))?
+            }
+            TokenType::String => ExprKind::Literal(Literal::String(token.name)),
+            TokenType::Char => {
+                ExprKind::Literal(Literal::Char(token.name.chars().next().unwrap_or('\0')))
+            }
+            TokenType::Keyword if token.name == "true" => ExprKind::Literal(Literal::Bool(true)),
+            TokenType::Keyword if token.name == "false" => ExprKind::Literal(Literal::Bool(false)),
+            TokenType::Name if self.check(TokenType::LsBkt) => {
+                self.next();
+                let mut args = Vec::new();
+                while !self.eat(TokenType::RsBkt) {
+                    args.push(self.parse_expr()?);
+                    if !self.check(TokenType::RsBkt) {
+                        self.expect(TokenType::Split, "',' or ')'")?;
+                    }
+                }
+                ExprKind::Call {
+                    name: Ident {
+                        name: token.name,
+                        span: token.span,
+                    },
+                    args,
+                }
+            }
+            TokenType::Name => ExprKind::Identifier(token.name),
+            TokenType::LsBkt => {
+                let expr = self.parse_expr()?;
+                self.expect(TokenType::RsBkt, "')'")?;
+                expr.kind
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected("expression"));
+            }
+        };
+        let mut expr = Expr {
+            kind,
+            span: self.span_from(start),
+            r#type: None,
+        };
+        /* postfixes: `[index]` and `.member` */
+        loop {
+            if self.eat(TokenType::LmBkt) {
+                let index = self.parse_expr()?;
+                self.expect(TokenType::RmBkt, "']'")?;
+                expr = Expr {
+                    kind: ExprKind::Index {
+                        base: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                    span: self.span_from(start),
+                    r#type: None,
+                };
+            } else if self.eat(TokenType::Dot) {
+                let member = self.expect_ident("member name")?;
+                expr = Expr {
+                    kind: ExprKind::Member {
+                        base: Box::new(expr),
+                        member,
+                    },
+                    span: self.span_from(start),
+                    r#type: None,
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+    fn parse_number(&self, text: &str, span: Span) -> Result<ExprKind, ParseError> {
+        let (value, suffix) = parse_integer(text).map_err(|e| ParseError {
+            kind: ParseErrorKind::InvalidLiteral(e),
+            span,
+        })?;
+        Ok(ExprKind::Literal(Literal::Integer { value, suffix }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** parse `code` as the value of an assignment and print it as an S-expression */
+    fn parse(code: &str) -> String {
+        let tokens = generate_token(&format!("func f() {{ x = {code} }}")).unwrap();
+        let ast = Ast::from_tokens(tokens).unwrap();
+        let Item::Func(func) = &ast.items[0] else {
+            panic!("not a function");
+        };
+        let StmtKind::Assign { value, .. } = &func.body.stmts[0].kind else {
+            panic!("not an assignment");
+        };
+        print_expr(value)
+    }
+
+    fn print_expr(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Integer { value, .. }) => value.to_string(),
+            ExprKind::Identifier(name) => name.clone(),
+            ExprKind::Binary { op, left, right } => {
+                format!("({op} {} {})", print_expr(left), print_expr(right))
+            }
+            ExprKind::Unary { op, operand } => format!("({op} {})", print_expr(operand)),
+            ExprKind::Cast { operand, target } => {
+                format!("(as {} {})", print_expr(operand), target.name)
+            }
+            other => panic!("unexpected node in print_expr: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn cast_over_binary() {
+        assert_eq!(parse("a as u8 + b"), "(+ (as a u8) b)");
+        assert_eq!(parse("-a as u8 * b as u8"), "(* (as (- a) u8) (as b u8))");
+        assert_eq!(parse("a as i64 as u16"), "(as (as a i64) u16)");
+    }
+
+    #[test]
+    fn multiplicative_over_additive() {
+        assert_eq!(parse("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(parse("1 * 2 - 3"), "(- (* 1 2) 3)");
+        assert_eq!(parse("a - b / c"), "(- a (/ b c))");
+        assert_eq!(parse("a + b % c"), "(+ a (% b c))");
+    }
+
+    #[test]
+    fn additive_over_shift() {
+        assert_eq!(parse("a << b + c"), "(<< a (+ b c))");
+        assert_eq!(parse("a - b >> c"), "(>> (- a b) c)");
+    }
+
+    #[test]
+    fn shift_over_relational() {
+        assert_eq!(parse("a < b << c"), "(< a (<< b c))");
+        assert_eq!(parse("a >> b >= c"), "(>= (>> a b) c)");
+    }
+
+    #[test]
+    fn relational_over_equality() {
+        assert_eq!(parse("a == b < c"), "(== a (< b c))");
+        assert_eq!(parse("a > b != c <= d"), "(!= (> a b) (<= c d))");
+    }
+
+    #[test]
+    fn equality_over_bitwise() {
+        assert_eq!(parse("a & b == c"), "(& a (== b c))");
+        assert_eq!(parse("n - 1 == 0"), "(== (- n 1) 0)");
+    }
+
+    #[test]
+    fn bitwise_and_xor_or() {
+        assert_eq!(parse("a ^ b & c"), "(^ a (& b c))");
+        assert_eq!(parse("a | b ^ c"), "(| a (^ b c))");
+        assert_eq!(parse("a & b | c ^ d"), "(| (& a b) (^ c d))");
+    }
+
+    #[test]
+    fn bitwise_over_logic() {
+        assert_eq!(parse("a && b | c"), "(&& a (| b c))");
+        assert_eq!(parse("a || b && c"), "(|| a (&& b c))");
+        assert_eq!(parse("n == 1 || n == 2"), "(|| (== n 1) (== n 2))");
+    }
+
+    #[test]
+    fn left_associative() {
+        assert_eq!(parse("a - b - c"), "(- (- a b) c)");
+        assert_eq!(parse("a / b * c"), "(* (/ a b) c)");
+        assert_eq!(parse("a << b >> c"), "(>> (<< a b) c)");
+        assert_eq!(parse("a || b || c"), "(|| (|| a b) c)");
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(parse("(a + b) * c"), "(* (+ a b) c)");
+        assert_eq!(parse("a - (b - c)"), "(- a (- b c))");
+        assert_eq!(parse("((a))"), "a");
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(parse("-x * y"), "(* (- x) y)");
+        assert_eq!(parse("!a && b"), "(&& (! a) b)");
+        assert_eq!(parse("~a & b"), "(& (~ a) b)");
+        assert_eq!(parse("- -x"), "(- (- x))");
+        assert_eq!(parse("-(a + b)"), "(- (+ a b))");
+        assert_eq!(parse("a - -1"), "(- a -1)");
+        assert_eq!(parse("-128i8"), "-128");
+    }
+
+    /** parse a whole program and print its errors */
+    fn errors(code: &str) -> Vec<String> {
+        let tokens = generate_token(code).unwrap();
+        match Ast::from_tokens(tokens) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert_eq!(
+            errors("return"),
+            ["1:1: expected 'func' or 'var', found 'return'"]
+        );
+        assert_eq!(
+            errors("func"),
+            ["1:5: expected function name, found end of file"]
+        );
+        assert_eq!(
+            errors("func () {}"),
+            ["1:6: expected function name, found '('"]
+        );
+        assert_eq!(
+            errors("func f() { = 1; }"),
+            ["1:12: expected expression, found '='"]
+        );
+        assert_eq!(
+            errors("func f() { x = 1"),
+            ["1:17: expected '}', found end of file"]
+        );
+        assert_eq!(
+            errors("func f() { x = 0x; }"),
+            ["1:16: '0x' is not a number."]
+        );
+        assert_eq!(
+            errors("func f() { for (var i: u64 = 0, i < 3, i++) { } }"),
+            ["1:31: expected ';', found ','"]
+        );
+        assert_eq!(
+            errors("func f() { for (return; ;) { } }")[0],
+            "1:17: expected variable declaration, assignment or expression, found 'return'"
+        );
+        assert_eq!(
+            errors("func f() { for (; ; var i: u8 = 0) { } }")[0],
+            "1:21: expected assignment or expression, found 'var'"
+        );
+        assert_eq!(
+            errors("func f() { for (; n < 100; break) { } }")[0],
+            "1:28: expected assignment or expression, found 'break'"
+        );
+        assert_eq!(
+            errors("func f() { for (; ; { i++; }) { } }")[0],
+            "1:21: expected assignment or expression, found '{'"
+        );
+    }
+
+    #[test]
+    fn recover_at_statements() {
+        let code = "func f() {\n    var a u8;\n    a = ;\n    if a { b = ) }\n    return a;\n}\nx = 1;\nfunc g() { y = * }";
+        assert_eq!(
+            errors(code),
+            [
+                "2:11: expected ':' or '=', found 'u8'",
+                "3:9: expected expression, found ';'",
+                "4:16: expected expression, found ')'",
+                "7:1: expected 'func' or 'var', found 'x'",
+                "8:16: expected expression, found '*'",
+            ]
+        );
+    }
+}
@@ -29,13 +29,9 @@ impl Symbols {
         self.internal_syms.push((id, addr));
         id
     }
-    /** Add a reference */
-    pub fn external_reference(&mut self, symbol: &str, addr: u64) -> Result<(), String> {
-        if self.lookup(symbol).is_none() {
-            return Err(format!("'{}' not defined", symbol));
-        }
+    /** Add a reference, the symbol can be defined later until linking */
+    pub fn external_reference(&mut self, symbol: &str, addr: u64) {
         self.external_refs.push((symbol.to_string(), addr));
-        Ok(())
     }
     /** Add an internal reference */
     pub fn internal_reference(&mut self, symbol: usize, addr: u64) {
@@ -45,9 +41,13 @@ impl Symbols {
     pub fn modify_internal_sym(&mut self, id: usize, addr: u64) {
         self.internal_syms[id].1 = addr;
     }
-    pub fn link(&self, byte_code: &mut [u8]) {
+    /** Fill the addresses of symbols into references */
+    pub fn link(&self, byte_code: &mut [u8]) -> Result<(), String> {
         for sym in &self.external_refs {
-            let addr = self.lookup(&sym.0).unwrap();
+            let addr = match self.lookup(&sym.0) {
+                Some(addr) => addr,
+                None => return Err(format!("'{}' not defined", sym.0)),
+            };
             for i in 0..8 {
                 byte_code[sym.1 as usize + i] = addr.to_be_bytes()[i];
             }
@@ -59,6 +59,7 @@ impl Symbols {
                 byte_code[ref_i.1 as usize + i] = addr.to_be_bytes()[i];
             }
         }
+        Ok(())
     }
     /** lookup external symbol */
     pub fn lookup(&self, name: &str) -> Option<u64> {
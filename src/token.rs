@@ -1,7 +1,68 @@
+/** A location in the source code */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    /** byte offset from the start of the file */
+    pub offset: usize,
+    /** line number, starts from 1 */
+    pub line: usize,
+    /** column number, starts from 1 */
+    pub column: usize,
+}
+
+/** The range of source code covered by a token or an AST node */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    /** position right after the last character */
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+    /** a span that doesn't point to any source code */
+    pub fn is_dummy(&self) -> bool {
+        self.start.line == 0
+    }
+    /** prefix an error message with this position */
+    pub fn error(&self, msg: &str) -> String {
+        format!("{self}: {msg}")
+    }
+    /** get the smallest span covering both spans */
+    pub fn merge(self, other: Span) -> Span {
+        if self.is_dummy() {
+            return other;
+        }
+        if other.is_dummy() {
+            return self;
+        }
+        Span {
+            start: if other.start.offset < self.start.offset {
+                other.start
+            } else {
+                self.start
+            },
+            end: if other.end.offset > self.end.offset {
+                other.end
+            } else {
+                self.end
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.start.line, self.start.column)
+    }
+}
+
 #[derive(Clone)]
 pub struct Token {
     pub r#type: TokenType,
     pub name: String,
+    pub span: Span,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -36,11 +97,26 @@ pub enum TokenType {
     Or,       // |
     Xor,      // ^
     Not,      // !
+    BitNot,   // ~
     LogicAnd, // &&
     LogicOr,  // ||
     Shl,      // <<
     Shr,      // >>
     Dot,      // .
+    AddEqu,   // +=
+    SubEqu,   // -=
+    MulEqu,   // *=
+    DivEqu,   // /=
+    ModEqu,   // %=
+    ShlEqu,   // <<=
+    ShrEqu,   // >>=
+    AndEqu,   // &=
+    OrEqu,    // |=
+    XorEqu,   // ^=
+    Inc,      // ++
+    Dec,      // --
+    Range,    // ..
+    Label,    // 'name
 }
 
 impl Token {
@@ -54,83 +130,50 @@ impl Default for Token {
         Token {
             r#type: TokenType::Unkown,
             name: String::new(),
+            span: Span::default(),
         }
     }
 }
 
-pub const KEYWORDS: [&str; 14] = [
-    "var", "func", "return", "if", "elif", "else", "for", "while", "break", "continue", "import",
-    "true", "false", "null",
+pub const KEYWORDS: [&str; 16] = [
+    "var", "func", "return", "if", "elif", "else", "for", "in", "while", "break", "continue",
+    "import", "true", "false", "null", "as",
 ];
 
-/** detect the positions of symbols */
-fn parse_tokens(str: &str) -> Result<Vec<Token>, &str> {
-    let mut ret: Vec<Token> = Vec::new();
-    let mut this_token = Token::new();
-    pub const SYMBOLS: &str = " \"\\=()[]{}<>,.:;+-*/&|!^\t\r\n";
-    let mut in_string = false;
-    let mut in_single_line_comment = false;
-    let mut in_multiple_line_comment = false;
-    let mut last_char = ' ';
-    for i in 0..str.len() {
-        this_token.name.push_str(&str[i..i + 1]);
-        for sym in SYMBOLS.chars() {
-            if str.as_bytes()[i] == sym as u8 {
-                if sym == '"' && last_char != '\\' {
-                    in_string = !in_string;
-                    continue;
-                }
-
-                /* start of single line comment */
-                if sym == '/' && last_char == '/' {
-                    in_single_line_comment = true;
-                    ret.pop();
-                    continue;
-                }
-                /* end of single line comment */
-                else if in_single_line_comment && sym == '\n' {
-                    in_single_line_comment = false;
-                }
-
-                /* start of single line comment */
-                if last_char == '/' && sym == '*' {
-                    in_multiple_line_comment = true;
-                    ret.pop();
-                    continue;
-                }
-                /* end of single line comment */
-                else if in_multiple_line_comment && last_char == '*' && sym == '/' {
-                    in_multiple_line_comment = false;
-                    ret.pop();
-                    continue;
-                }
-                /* if in a string, don't put an in-string synbol into the 'ret' list */
-                if !in_string && !in_single_line_comment && !in_multiple_line_comment {
-                    this_token.name.pop();
-                    if !this_token.name.is_empty() {
-                        ret.push(this_token);
-                        this_token = Token::new();
-                    }
+/** Kinds of errors found while scanning the source code */
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexErrorKind {
+    UnknownCharacter(char),
+    UnterminatedString,
+    UnterminatedChar,
+    UnterminatedComment,
+    EmptyChar,
+    /** an unknown or malformed escape sequence, like `\q` or `\x4` */
+    InvalidEscape(String),
+}
 
-                    if sym != ' ' && sym != '\t' && sym != '\n' && sym != '\r' {
-                        let mut symbol_token = Token::new();
-                        symbol_token.name.push_str(&str[i..i + 1]);
-                        ret.push(symbol_token);
-                    }
-                }
+/** An error found while scanning the source code */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    /** where the erroneous token starts */
+    pub pos: Position,
+}
 
-                last_char = sym;
-                break;
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.pos.line, self.pos.column)?;
+        match &self.kind {
+            LexErrorKind::UnknownCharacter(c) => write!(f, "unknown character '{c}'"),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::UnterminatedChar => write!(f, "unterminated char literal"),
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorKind::EmptyChar => write!(f, "empty char literal"),
+            LexErrorKind::InvalidEscape(escape) => {
+                write!(f, "invalid escape sequence '{escape}'")
             }
         }
     }
-    if !this_token.name.is_empty() {
-        ret.push(this_token);
-    }
-    if in_string {
-        return Err("sybmol '\"' doesn't match.");
-    }
-    Ok(ret)
 }
 
 /** detect if a keyword */
@@ -143,125 +186,433 @@ fn is_keyword(str: &str) -> bool {
     false
 }
 
-/** detect if a number */
-fn is_number(str: &str) -> bool {
-    for i in str.chars() {
-        if !i.is_numeric() {
-            return false;
-        }
-    }
-    true
+/** A character-level scanner over the source code */
+struct Scanner<'a> {
+    code: &'a str,
+    chars: std::str::CharIndices<'a>,
+    /** position of the next character */
+    pos: Position,
 }
 
-/**
- Generate tokens
-*/
-pub fn generate_token(code: &str) -> Result<Vec<Token>, &str> {
-    let mut tokens = parse_tokens(code)?;
-
-    let mut i = 0;
-    /* detect types */
-    while i < tokens.len() {
-        tokens[i].r#type = TokenType::Name;
-        if is_keyword(&tokens[i].name) {
-            tokens[i].r#type = TokenType::Keyword;
-        } else if is_number(&tokens[i].name) {
-            tokens[i].r#type = TokenType::Number;
+impl<'a> Scanner<'a> {
+    fn new(code: &'a str) -> Self {
+        Scanner {
+            code,
+            chars: code.char_indices(),
+            pos: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
         }
-        /* comment */
-        else if tokens[i].name.starts_with("//") || tokens[i].name.starts_with("/*") {
-            tokens.remove(i);
-            i -= 1;
-        } else if tokens[i].name.starts_with('\"') && tokens[i].name.ends_with('\"') {
-            tokens[i].r#type = TokenType::String;
-            /* replace escape characters */
-            tokens[i].name = tokens[i].name.replace("\\\"", "\"");
-            tokens[i].name = tokens[i].name.replace("\\n", "\n");
-            tokens[i].name = tokens[i].name.replace("\\r", "\r");
-            tokens[i].name = tokens[i].name.replace("\\t", "\t");
-        } else if tokens[i].name.len() == 3
-            && tokens[i].name.as_bytes()[0] == b'\''
-            && tokens[i].name.as_bytes()[2] == b'\''
-        {
-            tokens[i].r#type = TokenType::Char;
-        } else if tokens[i].name == "&" && tokens[i + 1].name == "&" {
-            tokens[i].r#type = TokenType::LogicAnd;
-            tokens[i].name = "&&".to_string();
-            tokens.remove(i + 1);
-        } else if tokens[i].name == "|" && tokens[i + 1].name == "|" {
-            tokens[i].r#type = TokenType::LogicOr;
-            tokens[i].name = "||".to_string();
-            tokens.remove(i + 1);
-        } else if tokens[i].name == "=" && tokens[i + 1].name == "=" {
-            tokens[i].r#type = TokenType::IsEqu;
-            tokens[i].name = "==".to_string();
-            tokens.remove(i + 1);
-        } else if tokens[i].name == "!" && tokens[i + 1].name == "=" {
-            tokens[i].r#type = TokenType::NotEqu;
-            tokens[i].name = "!=".to_string();
-            tokens.remove(i + 1);
-        } else if tokens[i].name == "-" && tokens[i + 1].name == ">" {
-            tokens[i].r#type = TokenType::Explain;
-            tokens[i].name = "->".to_string();
-            tokens.remove(i + 1);
+    }
+    /** look at the next character without consuming it */
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next().map(|(_, c)| c)
+    }
+    /** look at the character after the next one */
+    fn peek_second(&self) -> Option<char> {
+        self.chars.clone().nth(1).map(|(_, c)| c)
+    }
+    /** consume the next character */
+    fn bump(&mut self) -> Option<char> {
+        let (offset, c) = self.chars.next()?;
+        self.pos.offset = offset + c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+    /** consume the next character if it is `expected` */
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+    /** skip white spaces and comments */
+    fn skip_trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                /* single line comment */
+                (Some('/'), Some('/')) => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+                /* multiple line comment, which can be nested */
+                (Some('/'), Some('*')) => {
+                    let start = self.pos;
+                    self.bump();
+                    self.bump();
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match (self.bump(), self.peek()) {
+                            (Some('/'), Some('*')) => {
+                                self.bump();
+                                depth += 1;
+                            }
+                            (Some('*'), Some('/')) => {
+                                self.bump();
+                                depth -= 1;
+                            }
+                            (Some(_), _) => {}
+                            (None, _) => {
+                                return Err(LexError {
+                                    kind: LexErrorKind::UnterminatedComment,
+                                    pos: start,
+                                })
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+    /** scan at most `max` hex digits, they are also appended to `text` */
+    fn scan_hex(&mut self, text: &mut String, max: usize) -> Option<u32> {
+        let mut value = None;
+        for _ in 0..max {
+            let digit = match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => digit,
+                None => break,
+            };
+            text.extend(self.bump());
+            value = Some(value.unwrap_or(0) * 16 + digit);
+        }
+        value
+    }
+    /**
+     * Scan an escape sequence, the backslash at `start` has been consumed
+     *
+     * Supported: `\n`, `\r`, `\t`, `\0`, `\\`, `\'`, `\"`, `\xNN` and `\u{NNNN}`.
+     * Strings are stored as UTF-8, so `\xNN` is limited to ASCII in them like Rust does,
+     * a char literal takes any byte.
+     */
+    fn scan_escape(
+        &mut self,
+        start: Position,
+        unterminated: &LexError,
+        in_string: bool,
+    ) -> Result<char, LexError> {
+        let mut text = String::from('\\');
+        let c = self.bump().ok_or(unterminated.clone())?;
+        text.push(c);
+        let escaped = match c {
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            '0' => Some('\0'),
+            '\\' | '\'' | '"' => Some(c),
+            'x' => match self.scan_hex(&mut text, 2) {
+                Some(value) if text.len() == 4 && (!in_string || value <= 0x7f) => {
+                    char::from_u32(value)
+                }
+                _ => None,
+            },
+            'u' if self.eat('{') => {
+                text.push('{');
+                let value = self.scan_hex(&mut text, 6);
+                if self.eat('}') {
+                    text.push('}');
+                    value.and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        escaped.ok_or(LexError {
+            kind: LexErrorKind::InvalidEscape(text),
+            pos: start,
+        })
+    }
+    /** scan a string literal, the opening quote has been consumed */
+    fn scan_string(&mut self, start: Position) -> Result<String, LexError> {
+        let mut str = String::new();
+        let error = LexError {
+            kind: LexErrorKind::UnterminatedString,
+            pos: start,
+        };
+        loop {
+            let pos = self.pos;
+            match self.bump() {
+                Some('"') => return Ok(str),
+                Some('\\') => str.push(self.scan_escape(pos, &error, true)?),
+                Some(c) => str.push(c),
+                None => return Err(error),
+            }
         }
-        /* << */
-        else if tokens[i].name == "<" && tokens[i + 1].name == "<" {
-            tokens[i].name = "<<".to_string();
-            tokens[i].r#type = TokenType::Shl;
-            tokens.remove(i + 1);
+    }
+    /** scan a char literal, the opening quote has been consumed */
+    fn scan_char(&mut self, start: Position) -> Result<String, LexError> {
+        let error = LexError {
+            kind: LexErrorKind::UnterminatedChar,
+            pos: start,
+        };
+        let pos = self.pos;
+        let c = match self.bump() {
+            Some('\'') => {
+                return Err(LexError {
+                    kind: LexErrorKind::EmptyChar,
+                    pos: start,
+                })
+            }
+            Some('\\') => self.scan_escape(pos, &error, false)?,
+            Some('\n') | None => return Err(error),
+            Some(c) => c,
+        };
+        if !self.eat('\'') {
+            return Err(error);
         }
-        /* >> */
-        else if tokens[i].name == ">" && tokens[i + 1].name == ">" {
-            tokens[i].name = ">>".to_string();
-            tokens[i].r#type = TokenType::Shr;
-            tokens.remove(i + 1);
+        Ok(c.to_string())
+    }
+    /** scan the rest of a word made of letters, digits and underlines */
+    fn scan_word(&mut self, first: char) -> String {
+        let mut word = first.to_string();
+        while let Some(c) = self.peek() {
+            if !c.is_alphanumeric() && c != '_' {
+                break;
+            }
+            word.push(c);
+            self.bump();
         }
-        /* <, >, <=, >= */
-        else if tokens[i].name == ">" || tokens[i].name == "<" {
-            /* >= or <= */
-            if tokens[i + 1].name == "=" {
-                if tokens[i].name == ">" {
-                    tokens[i].name = ">=".to_string();
-                    tokens[i].r#type = TokenType::Ge;
+        word
+    }
+    /** scan an operator or a punctuation */
+    fn scan_symbol(&mut self, first: char, start: Position) -> Result<TokenType, LexError> {
+        let r#type = match first {
+            '=' if self.eat('=') => TokenType::IsEqu,
+            '!' if self.eat('=') => TokenType::NotEqu,
+            '<' if self.eat('=') => TokenType::Le,
+            '>' if self.eat('=') => TokenType::Ge,
+            '<' if self.eat('<') => {
+                if self.eat('=') {
+                    TokenType::ShlEqu
                 } else {
-                    tokens[i].name = "<=".to_string();
-                    tokens[i].r#type = TokenType::Le;
+                    TokenType::Shl
+                }
+            }
+            '>' if self.eat('>') => {
+                if self.eat('=') {
+                    TokenType::ShrEqu
+                } else {
+                    TokenType::Shr
                 }
-
-                tokens.remove(i + 1);
-            } else if tokens[i].name == ">" {
-                tokens[i].r#type = TokenType::GT;
-            } else {
-                tokens[i].r#type = TokenType::LT;
             }
-        } else if tokens[i].name.len() == 1 {
-            match &tokens[i].name[..] {
-                "&" => tokens[i].r#type = TokenType::And,
-                "|" => tokens[i].r#type = TokenType::Or,
-                "^" => tokens[i].r#type = TokenType::Xor,
-                "!" => tokens[i].r#type = TokenType::Not,
-                "=" => tokens[i].r#type = TokenType::Equ,
-                ":" => tokens[i].r#type = TokenType::Explain,
-                "+" => tokens[i].r#type = TokenType::Add,
-                "-" => tokens[i].r#type = TokenType::Sub,
-                "*" => tokens[i].r#type = TokenType::Mul,
-                "/" => tokens[i].r#type = TokenType::Div,
-                "%" => tokens[i].r#type = TokenType::Mod,
-                "(" => tokens[i].r#type = TokenType::LsBkt,
-                "[" => tokens[i].r#type = TokenType::LmBkt,
-                "{" => tokens[i].r#type = TokenType::LlBkt,
-                ")" => tokens[i].r#type = TokenType::RsBkt,
-                "]" => tokens[i].r#type = TokenType::RmBkt,
-                "}" => tokens[i].r#type = TokenType::RlBkt,
-                "," => tokens[i].r#type = TokenType::Split,
-                ";" => tokens[i].r#type = TokenType::Split,
-                "." => tokens[i].r#type = TokenType::Dot,
-                _ => {}
+            '&' if self.eat('&') => TokenType::LogicAnd,
+            '|' if self.eat('|') => TokenType::LogicOr,
+            '-' if self.eat('>') => TokenType::Explain,
+            '+' if self.eat('=') => TokenType::AddEqu,
+            '-' if self.eat('=') => TokenType::SubEqu,
+            '*' if self.eat('=') => TokenType::MulEqu,
+            '/' if self.eat('=') => TokenType::DivEqu,
+            '%' if self.eat('=') => TokenType::ModEqu,
+            '&' if self.eat('=') => TokenType::AndEqu,
+            '|' if self.eat('=') => TokenType::OrEqu,
+            '^' if self.eat('=') => TokenType::XorEqu,
+            '+' if self.eat('+') => TokenType::Inc,
+            '-' if self.eat('-') => TokenType::Dec,
+            '=' => TokenType::Equ,
+            '!' => TokenType::Not,
+            '~' => TokenType::BitNot,
+            '<' => TokenType::LT,
+            '>' => TokenType::GT,
+            '&' => TokenType::And,
+            '|' => TokenType::Or,
+            '^' => TokenType::Xor,
+            ':' => TokenType::Explain,
+            '+' => TokenType::Add,
+            '-' => TokenType::Sub,
+            '*' => TokenType::Mul,
+            '/' => TokenType::Div,
+            '%' => TokenType::Mod,
+            '(' => TokenType::LsBkt,
+            '[' => TokenType::LmBkt,
+            '{' => TokenType::LlBkt,
+            ')' => TokenType::RsBkt,
+            ']' => TokenType::RmBkt,
+            '}' => TokenType::RlBkt,
+            ',' | ';' => TokenType::Split,
+            '.' if self.eat('.') => TokenType::Range,
+            '.' => TokenType::Dot,
+            c => {
+                return Err(LexError {
+                    kind: LexErrorKind::UnknownCharacter(c),
+                    pos: start,
+                })
             }
+        };
+        Ok(r#type)
+    }
+    /** scan the next token, returns `None` at the end of the code */
+    fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let first = match self.bump() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let mut token = Token::new();
+        if first.is_alphabetic() || first == '_' {
+            token.name = self.scan_word(first);
+            token.r#type = if is_keyword(&token.name) {
+                TokenType::Keyword
+            } else {
+                TokenType::Name
+            };
+        } else if first.is_ascii_digit() {
+            token.name = self.scan_word(first);
+            token.r#type = TokenType::Number;
+        } else if first == '"' {
+            token.name = self.scan_string(start)?;
+            token.r#type = TokenType::String;
         }
-        i += 1;
+        /* `'name` is a label unless it is closed like `'a'` */
+        else if first == '\''
+            && self.peek().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && self.peek_second() != Some('\'')
+        {
+            let first = self.bump().unwrap();
+            token.name = format!("'{}", self.scan_word(first));
+            token.r#type = TokenType::Label;
+        } else if first == '\'' {
+            token.name = self.scan_char(start)?;
+            token.r#type = TokenType::Char;
+        } else {
+            token.r#type = self.scan_symbol(first, start)?;
+            token.name = self.code[start.offset..self.pos.offset].to_string();
+        }
+        token.span = Span::new(start, self.pos);
+        Ok(Some(token))
     }
+}
 
+/**
+ Generate tokens
+
+ String and char tokens hold the unescaped content without quotes,
+ other tokens hold the source text.
+*/
+pub fn generate_token(code: &str) -> Result<Vec<Token>, LexError> {
+    let mut tokens = Vec::new();
+    let mut scanner = Scanner::new(code);
+    while let Some(token) = scanner.next_token()? {
+        tokens.push(token);
+    }
     Ok(tokens)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** scan `code` into the type and the text of each token */
+    fn scan(code: &str) -> Vec<(TokenType, String)> {
+        generate_token(code)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.r#type, token.name))
+            .collect()
+    }
+
+    fn error(code: &str) -> String {
+        generate_token(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn utf8_source() {
+        let tokens = generate_token("// 注释 ☕\nvar größe /* ✓ */ = 1;").unwrap();
+        let names: Vec<_> = tokens.iter().map(|token| token.name.as_str()).collect();
+        assert_eq!(names, ["var", "größe", "=", "1", ";"]);
+        assert_eq!(tokens[1].r#type, TokenType::Name);
+        /* columns count characters, offsets count bytes */
+        assert_eq!(tokens[2].span.start.line, 2);
+        assert_eq!(tokens[2].span.start.column, 19);
+        assert_eq!(tokens[2].span.start.offset, 36);
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(
+            scan("a /* outer /* inner */ still a comment */ b"),
+            [
+                (TokenType::Name, "a".to_string()),
+                (TokenType::Name, "b".to_string())
+            ]
+        );
+        assert_eq!(
+            error("a\n  /* outer /* inner */"),
+            "2:3: unterminated block comment"
+        );
+        assert_eq!(error("/*"), "1:1: unterminated block comment");
+    }
+
+    #[test]
+    fn unknown_character() {
+        assert_eq!(
+            error("var a = 1;\nb = a @ 2;"),
+            "2:7: unknown character '@'"
+        );
+        assert_eq!(error("é = $"), "1:5: unknown character '$'");
+    }
+
+    #[test]
+    fn symbol_at_end_of_file() {
+        for (code, r#type) in [
+            ("<", TokenType::LT),
+            ("&", TokenType::And),
+            ("=", TokenType::Equ),
+            ("<<", TokenType::Shl),
+            ("a >>", TokenType::Shr),
+        ] {
+            let tokens = scan(code);
+            assert_eq!(tokens.last().unwrap().0, r#type, "{code}");
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            scan(r#""a\n\r\t\0\\\'\"\x41\u{4F60}""#),
+            [(TokenType::String, "a\n\r\t\0\\'\"A你".to_string())]
+        );
+        assert_eq!(
+            scan(r"'\n' '\'' '\\' '\xFF' '\u{1F600}' 'a'"),
+            [
+                (TokenType::Char, "\n".to_string()),
+                (TokenType::Char, "'".to_string()),
+                (TokenType::Char, "\\".to_string()),
+                (TokenType::Char, "\u{ff}".to_string()),
+                (TokenType::Char, "😀".to_string()),
+                (TokenType::Char, "a".to_string()),
+            ]
+        );
+        /* a string is UTF-8, a byte above 0x7F would become two bytes */
+        assert_eq!(
+            error(r#"var s = "ok\xFF";"#),
+            r"1:12: invalid escape sequence '\xFF'"
+        );
+        assert_eq!(error(r#""\q""#), r"1:2: invalid escape sequence '\q'");
+        assert_eq!(error(r"'\x4'"), r"1:2: invalid escape sequence '\x4'");
+        assert_eq!(
+            error(r#""\u{110000}""#),
+            r"1:2: invalid escape sequence '\u{110000}'"
+        );
+        assert_eq!(error(r"'\u{41'"), r"1:2: invalid escape sequence '\u{41'");
+        assert_eq!(error(r#""abc\"#), "1:1: unterminated string literal");
+        assert_eq!(error("''"), "1:1: empty char literal");
+        assert_eq!(error("'12'"), "1:1: unterminated char literal");
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum VariableType {
     Uint8,
     Int8,
@@ -27,6 +27,36 @@ impl VariableType {
             _ => Self::Unkown,
         }
     }
+    /** get the type of an integer literal suffix like `u8` in `255u8` */
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match Self::from_string(suffix) {
+            Self::Bool | Self::Unkown => None,
+            var_type => Some(var_type),
+        }
+    }
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::Int8 | Self::Int16 | Self::Int32 | Self::Int64)
+    }
+    /** get the minimum and maximum value of this type */
+    pub fn get_range(&self) -> (i128, i128) {
+        match self {
+            Self::Uint8 => (0, u8::MAX as i128),
+            Self::Int8 => (i8::MIN as i128, i8::MAX as i128),
+            Self::Uint16 => (0, u16::MAX as i128),
+            Self::Int16 => (i16::MIN as i128, i16::MAX as i128),
+            Self::Uint32 => (0, u32::MAX as i128),
+            Self::Int32 => (i32::MIN as i128, i32::MAX as i128),
+            Self::Uint64 => (0, u64::MAX as i128),
+            Self::Int64 => (i64::MIN as i128, i64::MAX as i128),
+            Self::Bool => (0, 1),
+            Self::Unkown => (i64::MIN as i128, u64::MAX as i128),
+        }
+    }
+    /** detect if a value can be represented by this type */
+    pub fn contains(&self, value: i128) -> bool {
+        let (min, max) = self.get_range();
+        value >= min && value <= max
+    }
     pub fn get_size(&self) -> usize {
         match self {
             Self::Uint8 => 1,
@@ -43,12 +73,31 @@ impl VariableType {
     }
 }
 
-#[derive(Debug)]
+impl std::fmt::Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Uint8 => "u8",
+            Self::Int8 => "i8",
+            Self::Uint16 => "u16",
+            Self::Int16 => "i16",
+            Self::Uint32 => "u32",
+            Self::Int32 => "i32",
+            Self::Uint64 => "u64",
+            Self::Int64 => "i64",
+            Self::Bool => "bool",
+            Self::Unkown => "unknown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Variable {
     pub name: String,
     pub size: usize,
     pub r#type: VariableType,
-    pub offset: usize,
+    /** address relative to the frame pointer */
+    pub offset: isize,
 }
 
 impl Variable {
@@ -82,34 +131,29 @@ impl<'a> LocalVariables<'a> {
      * Find variable in local variables
      *
      * For example:
-     * ```
+     * ```ignore
      * println!("{:?}", variables.lookup("i"));
      * ```
      * Output:
-     * ```
-     * Some(Variable { name: "i", size: 8, type: Uint64, offset: 0 })
+     * ```text
+     * Some(Variable { name: "i", size: 8, type: Uint64, offset: -8 })
      * ```
      */
-    pub fn lookup(&self, id: &str) -> Option<&Variable> {
+    pub fn lookup(&self, id: &str) -> Option<Variable> {
         for i in &self.variables {
             if i.name == id {
-                return Some(i);
+                return Some(i.clone());
             }
         }
-        if let Some(previous) = self.previous {
-            return previous.lookup(id);
-        }
-        None
+        self.previous?.lookup(id)
     }
-    pub fn modify_offset(&mut self, offset: isize) {
-        for i in 0..self.variables.len() {
-            if offset > 0 {
-                self.variables[i].offset += offset as usize;
-            } else {
-                let offset: usize = (-offset).try_into().unwrap();
-                self.variables[i].offset -= offset;
-            }
-        }
+    /** get the stack size of variables in this scope */
+    pub fn size(&self) -> usize {
+        self.variables.iter().map(|var| var.size).sum()
+    }
+    /** get the stack size of variables in this scope and all upper scopes */
+    pub fn stack_size(&self) -> usize {
+        self.size() + self.previous.map_or(0, |previous| previous.stack_size())
     }
     pub fn push(&mut self, var: Variable) -> Result<(), String> {
         if self.lookup(&var.name).is_some() {
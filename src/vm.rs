@@ -8,7 +8,7 @@ pub const VM_STACK_SIZE: usize = 8 * 1024 * 1024;
 pub const VM_BITS: usize = 64;
 pub const VM_USIZE: usize = VM_BITS / 8;
 
-// op codes (0x01 - 0x22)
+// op codes (0x01 - 0x31)
 pub const VM_OP_MOV: u8 = 0x01;
 pub const VM_OP_IN: u8 = 0x02;
 pub const VM_OP_OUT: u8 = 0x03;
@@ -45,6 +45,21 @@ pub const VM_OP_TESTLE: u8 = 0x22;
 pub const VM_OP_JE: u8 = 0x23;
 pub const VM_OP_JNE: u8 = 0x24;
 pub const VM_OP_HAL: u8 = 0x25;
+/* load and sign-extend */
+pub const VM_OP_LOADS8: u8 = 0x26;
+pub const VM_OP_LOADS16: u8 = 0x27;
+pub const VM_OP_LOADS32: u8 = 0x28;
+/* signed arithmetic and comparisons */
+pub const VM_OP_SDIV: u8 = 0x29;
+pub const VM_OP_SMOD: u8 = 0x2a;
+pub const VM_OP_SAR: u8 = 0x2b;
+pub const VM_OP_STESTGT: u8 = 0x2c;
+pub const VM_OP_STESTLT: u8 = 0x2d;
+pub const VM_OP_STESTGE: u8 = 0x2e;
+pub const VM_OP_STESTLE: u8 = 0x2f;
+/* trap if the result of the last operation does not fit in a type */
+pub const VM_OP_CHKU: u8 = 0x30;
+pub const VM_OP_CHKS: u8 = 0x31;
 
 pub const VM_REG_C0: u8 = 0x20;
 pub const VM_REG_C1: u8 = 0x21;
@@ -53,6 +68,7 @@ pub const VM_REG_C3: u8 = 0x23;
 pub const VM_REG_SP: u8 = 0x24;
 pub const VM_REG_IP: u8 = 0x25;
 pub const VM_REG_AR: u8 = 0x26;
+pub const VM_REG_FP: u8 = 0x27;
 
 /*
 value types (0x20 - 0x32)
@@ -138,6 +154,83 @@ impl OPcode {
     }
 }
 
+/** an error stopping the VM, `ip` is the address of the faulting instruction */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trap {
+    Overflow { ip: u64 },
+    DivisionByZero { ip: u64 },
+}
+
+impl std::fmt::Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overflow { ip } => write!(f, "arithmetic overflow at 0x{ip:08X}"),
+            Self::DivisionByZero { ip } => write!(f, "division by zero at 0x{ip:08X}"),
+        }
+    }
+}
+
+/**
+ * Compute a binary operation, wrapping around at 64 bits.
+ * Returns the result, whether it overflowed as unsigned (carry) and as signed (overflow).
+ */
+fn arithmetic(op: u8, source: u64, target: u64) -> (u64, bool, bool) {
+    let (signed_source, signed_target) = (source as i64, target as i64);
+    match op {
+        VM_OP_ADD => {
+            let (result, carry) = source.overflowing_add(target);
+            (
+                result,
+                carry,
+                signed_source.overflowing_add(signed_target).1,
+            )
+        }
+        VM_OP_SUB => {
+            let (result, carry) = source.overflowing_sub(target);
+            (
+                result,
+                carry,
+                signed_source.overflowing_sub(signed_target).1,
+            )
+        }
+        VM_OP_MUL => {
+            let (result, carry) = source.overflowing_mul(target);
+            (
+                result,
+                carry,
+                signed_source.overflowing_mul(signed_target).1,
+            )
+        }
+        VM_OP_DIV => (source / target, false, false),
+        VM_OP_MOD => (source % target, false, false),
+        VM_OP_SDIV => {
+            let (result, overflow) = signed_source.overflowing_div(signed_target);
+            (result as u64, false, overflow)
+        }
+        VM_OP_SMOD => {
+            let (result, overflow) = signed_source.overflowing_rem(signed_target);
+            (result as u64, false, overflow)
+        }
+        VM_OP_AND => (source & target, false, false),
+        VM_OP_OR => (source | target, false, false),
+        VM_OP_XOR => (source ^ target, false, false),
+        /* shifting by 64 bits or more moves every bit out */
+        VM_OP_SHL => {
+            let result = if target < 64 { source << target } else { 0 };
+            /* shift back to find the bits moved out */
+            let (back, signed_back) = if target < 64 {
+                (result >> target, result as i64 >> target)
+            } else {
+                (0, 0)
+            };
+            (result, back != source, signed_back != signed_source)
+        }
+        VM_OP_SHR => (if target < 64 { source >> target } else { 0 }, false, false),
+        VM_OP_SAR => ((signed_source >> target.min(63)) as u64, false, false),
+        _ => (0, false, false),
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct VM {
     pub c0: u64,
@@ -150,10 +243,14 @@ pub struct VM {
     pub sp: u64,
     /** Address Register */
     pub ar: u64,
+    /** Frame Pointer */
+    pub fp: u64,
 
     /* flags */
     pub zf: bool,
     pub cf: bool,
+    /** set if the last arithmetic operation overflowed as signed */
+    pub of: bool,
     pub ram: Vram,
     pub code: Box<RefCell<Vec<u8>>>,
 }
@@ -179,6 +276,7 @@ impl VM {
             VM_REG_SP => self.sp = value,
             VM_REG_IP => self.ip = value,
             VM_REG_AR => self.ar = value,
+            VM_REG_FP => self.fp = value,
             _ => {}
         }
     }
@@ -191,12 +289,14 @@ impl VM {
             VM_REG_SP => self.sp,
             VM_REG_IP => self.ip,
             VM_REG_AR => self.ar,
+            VM_REG_FP => self.fp,
             _ => 0,
         }
     }
-    /** run VM */
-    pub fn run(&mut self) {
+    /** run until `hal`, returns a trap stopping the program */
+    pub fn run(&mut self) -> Result<(), Trap> {
         loop {
+            let ip = self.ip;
             let opcode = OPcode::from(self);
 
             /* load register, address */
@@ -230,6 +330,21 @@ impl VM {
                     self.set_register(register, u64::from_be_bytes(data));
                 }
             }
+            /* load register, address with sign extension */
+            if opcode.op == VM_OP_LOADS8 || opcode.op == VM_OP_LOADS16 || opcode.op == VM_OP_LOADS32
+            {
+                let address = opcode.get_value(1, self);
+                let value = match opcode.op {
+                    VM_OP_LOADS8 => self.ram.dump(address, 1)[0] as i8 as i64,
+                    VM_OP_LOADS16 => {
+                        i16::from_be_bytes(self.ram.dump(address, 2).try_into().unwrap()) as i64
+                    }
+                    _ => i32::from_be_bytes(self.ram.dump(address, 4).try_into().unwrap()) as i64,
+                };
+                if let AssemblyValue::Register(register) = opcode.values[0] {
+                    self.set_register(register, value as u64);
+                }
+            }
             /* store register, address */
             if opcode.op == VM_OP_STORE8
                 || opcode.op == VM_OP_STORE16
@@ -274,24 +389,41 @@ impl VM {
                 || opcode.op == VM_OP_XOR
                 || opcode.op == VM_OP_SHL
                 || opcode.op == VM_OP_SHR
+                || opcode.op == VM_OP_SDIV
+                || opcode.op == VM_OP_SMOD
+                || opcode.op == VM_OP_SAR
             {
                 let source = opcode.get_value(0, self);
                 let target = opcode.get_value(1, self);
 
+                /* there is no value to wrap a division by zero to */
+                if target == 0
+                    && (opcode.op == VM_OP_DIV
+                        || opcode.op == VM_OP_MOD
+                        || opcode.op == VM_OP_SDIV
+                        || opcode.op == VM_OP_SMOD)
+                {
+                    return Err(Trap::DivisionByZero { ip });
+                }
+                let (result, carry, overflow) = arithmetic(opcode.op, source, target);
+                self.cf = carry;
+                self.of = overflow;
+
                 if let AssemblyValue::Register(register) = opcode.values[0] {
-                    match opcode.op {
-                        VM_OP_ADD => self.set_register(register, source + target),
-                        VM_OP_SUB => self.set_register(register, source - target),
-                        VM_OP_MUL => self.set_register(register, source * target),
-                        VM_OP_DIV => self.set_register(register, source / target),
-                        VM_OP_MOD => self.set_register(register, source % target),
-                        VM_OP_AND => self.set_register(register, source & target),
-                        VM_OP_OR => self.set_register(register, source | target),
-                        VM_OP_XOR => self.set_register(register, source ^ target),
-                        VM_OP_SHL => self.set_register(register, source << target),
-                        VM_OP_SHR => self.set_register(register, source >> target),
-                        _ => {}
-                    }
+                    self.set_register(register, result);
+                }
+            }
+            /* chku/chks register, bits */
+            if opcode.op == VM_OP_CHKU || opcode.op == VM_OP_CHKS {
+                let value = opcode.get_value(0, self);
+                let bits = opcode.get_value(1, self);
+
+                let fits = match opcode.op {
+                    VM_OP_CHKU => !self.cf && (bits >= 64 || value >> bits == 0),
+                    _ => !self.of && matches!((value as i64) >> (bits - 1).min(63), 0 | -1),
+                };
+                if !fits {
+                    return Err(Trap::Overflow { ip });
                 }
             }
             /* push register */
@@ -318,6 +450,10 @@ impl VM {
                 || opcode.op == VM_OP_TESTLT
                 || opcode.op == VM_OP_TESTGE
                 || opcode.op == VM_OP_TESTLE
+                || opcode.op == VM_OP_STESTGT
+                || opcode.op == VM_OP_STESTLT
+                || opcode.op == VM_OP_STESTGE
+                || opcode.op == VM_OP_STESTLE
             {
                 let source = opcode.get_value(1, self);
                 let target = opcode.get_value(2, self);
@@ -329,6 +465,10 @@ impl VM {
                     VM_OP_TESTLT => source < target,
                     VM_OP_TESTGE => source >= target,
                     VM_OP_TESTLE => source <= target,
+                    VM_OP_STESTGT => source as i64 > target as i64,
+                    VM_OP_STESTLT => (source as i64) < target as i64,
+                    VM_OP_STESTGE => source as i64 >= target as i64,
+                    VM_OP_STESTLE => source as i64 <= target as i64,
                     _ => false,
                 };
 
@@ -400,12 +540,12 @@ impl VM {
             }
             /* hal */
             if opcode.op == VM_OP_HAL {
-                return;
+                return Ok(());
             }
         }
     }
     /** update VM opcode */
     pub fn update_code(&mut self, code: &[u8]) {
-        self.code = Box::new(RefCell::new(Vec::from(code)));
+        *self.code = RefCell::new(Vec::from(code));
     }
 }